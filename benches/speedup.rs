@@ -0,0 +1,314 @@
+//! Times the bulk operations against the element-at-a-time loops they replace,
+//! and the single-word leaves against the clusters they replace. Run with
+//! `cargo bench`; each line gives the median of several runs of the old way,
+//! then of the new one, and how many times faster the latter is.
+
+extern crate veb_rs;
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use veb_rs::VEBTree;
+
+const RUNS: usize = 15;
+
+// the median time taken by f, not counting dropping what it returns
+fn time<T, F: FnMut() -> T>(mut f: F) -> Duration {
+    let mut runs: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let out = black_box(f());
+            let elapsed = start.elapsed();
+            drop(out);
+            elapsed
+        })
+        .collect();
+    runs.sort();
+    runs[RUNS / 2]
+}
+
+// the median time taken by f on a fresh clone of tree, not counting the clone
+fn time_on<F: FnMut(&mut VEBTree)>(tree: &VEBTree, mut f: F) -> Duration {
+    let mut runs: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let mut tree = tree.clone();
+            let start = Instant::now();
+            f(black_box(&mut tree));
+            start.elapsed()
+        })
+        .collect();
+    runs.sort();
+    runs[RUNS / 2]
+}
+
+fn report(name: &str, naive: Duration, bulk: Duration) {
+    let speedup = naive.as_secs_f64() / bulk.as_secs_f64();
+    println!("{:<44} {:>10.2?} {:>10.2?} {:>6.1}x", name, naive, bulk, speedup);
+}
+
+// n pseudo-random values below universe, repeats included, in draw order
+fn random(universe: i64, n: usize, seed: u64) -> Vec<i64> {
+    let mut state = seed;
+    (0..n)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 16) as i64 % universe
+        })
+        .collect()
+}
+
+// n distinct values below universe in ascending order
+fn random_sorted(universe: i64, n: usize, seed: u64) -> Vec<i64> {
+    let mut values = random(universe, n + n / 4, seed);
+    values.sort_unstable();
+    values.dedup();
+    values.truncate(n);
+    values
+}
+
+fn from_sorted_slice() {
+    for &(universe, name) in &[(1 << 17, "2^17"), (1 << 20, "2^20"), (1 << 32, "2^32")] {
+        let elements = random_sorted(universe, 100_000, 1);
+        let naive = time(|| {
+            let mut tree = VEBTree::new(universe).unwrap();
+            for &x in &elements {
+                tree.insert(x);
+            }
+            tree
+        });
+        let bulk = time(|| VEBTree::from_sorted_slice(&elements, universe).unwrap());
+        report(&format!("from_sorted_slice, 100K of {}", name), naive, bulk);
+    }
+}
+
+fn bulk_insert() {
+    let spread = |universe| random(universe, 10_000, 5);
+    // the same number of keys, all within one 2^16 stretch of the universe
+    let local = || random(1 << 16, 10_000, 5).iter().map(|&x| x + (1 << 31)).collect();
+    let cases: [(i64, &str, Vec<i64>); 3] = [
+        (1 << 20, "2^20", spread(1 << 20)),
+        (1 << 32, "2^32", spread(1 << 32)),
+        (1 << 32, "2^32, local", local()),
+    ];
+    for (universe, name, keys) in &cases {
+        for &(existing, into) in &[(0, "empty"), (100_000, "100K")] {
+            let elements = random_sorted(*universe, existing, 6);
+            let base = VEBTree::from_sorted_slice(&elements, *universe).unwrap();
+            let naive = time_on(&base, |tree| {
+                for &x in keys {
+                    tree.insert(x);
+                }
+            });
+            let bulk = time_on(&base, |tree| {
+                tree.bulk_insert(keys.iter().cloned());
+            });
+            report(&format!("bulk_insert, 10K into {} of {}", into, name), naive, bulk);
+        }
+    }
+}
+
+fn bulk_delete() {
+    for &(universe, name, deleted) in &[(1 << 20, "2^20", 250_000), (1 << 32, "2^32", 500_000)] {
+        let base = VEBTree::from_sorted_slice(&random_sorted(universe, 500_000, 7), universe);
+        let base = base.unwrap();
+        // distinct present keys, shuffled
+        let mut keys: Vec<i64> = base.iter().collect();
+        for (i, r) in random(1 << 40, keys.len(), 8).into_iter().enumerate().rev() {
+            keys.swap(i, r as usize % (i + 1));
+        }
+        keys.truncate(deleted);
+        let naive = time_on(&base, |tree| {
+            for &x in &keys {
+                tree.delete(x);
+            }
+        });
+        let bulk = time_on(&base, |tree| {
+            tree.bulk_delete(keys.iter().cloned());
+        });
+        report(&format!("bulk_delete, {}K of 500K in {}", deleted / 1000, name), naive, bulk);
+    }
+}
+
+// The layout small trees had before they were stored as a single u64: clusters
+// all the way down to a universe-2 base case, with the minimum kept out of its
+// cluster. Kept here to time the leaves against.
+struct Recursive {
+    universe: i64,
+    shift: u32,
+    min: Option<i64>,
+    max: Option<i64>,
+    summary: Option<Box<Recursive>>,
+    clusters: HashMap<i64, Recursive>,
+}
+
+impl Recursive {
+    fn new(universe: i64) -> Recursive {
+        let bits = 64 - (universe - 1).leading_zeros();
+        Recursive {
+            universe,
+            shift: bits / 2,
+            min: None,
+            max: None,
+            summary: None,
+            clusters: HashMap::new(),
+        }
+    }
+
+    fn split(&self, x: i64) -> (i64, i64) {
+        (x >> self.shift, x & ((1 << self.shift) - 1))
+    }
+
+    fn has(&self, x: i64) -> bool {
+        if self.min == Some(x) || self.max == Some(x) {
+            return true;
+        }
+        let (high, low) = self.split(x);
+        self.universe > 2 && self.clusters.get(&high).is_some_and(|cluster| cluster.has(low))
+    }
+
+    fn insert(&mut self, mut x: i64) {
+        let min = match self.min {
+            Some(min) => min,
+            None => {
+                self.min = Some(x);
+                self.max = Some(x);
+                return;
+            }
+        };
+        if x == min {
+            return;
+        } else if x < min {
+            self.min = Some(x);
+            x = min;
+        }
+        if self.universe > 2 {
+            let (high, low) = self.split(x);
+            let size = 1 << self.shift;
+            let summary_size = ((self.universe - 1) >> self.shift) + 1;
+            let cluster = self.clusters.entry(high).or_insert_with(|| Recursive::new(size));
+            if cluster.min.is_none() {
+                let summary = Box::new(Recursive::new(summary_size));
+                self.summary.get_or_insert(summary).insert(high);
+            }
+            cluster.insert(low);
+        }
+        if self.max.is_none_or(|max| x > max) {
+            self.max = Some(x);
+        }
+    }
+
+    fn delete(&mut self, mut x: i64) {
+        if self.min == self.max {
+            if self.min == Some(x) {
+                self.min = None;
+                self.max = None;
+            }
+            return;
+        } else if self.universe == 2 {
+            self.min = Some(1 - x);
+            self.max = self.min;
+            return;
+        }
+        if self.min == Some(x) {
+            let first = self.summary.as_ref().unwrap().min.unwrap();
+            x = first << self.shift | self.clusters[&first].min.unwrap();
+            self.min = Some(x);
+        }
+        let (high, low) = self.split(x);
+        let emptied = match self.clusters.get_mut(&high) {
+            Some(cluster) => {
+                cluster.delete(low);
+                cluster.min.is_none()
+            }
+            None => return,
+        };
+        if emptied {
+            self.clusters.remove(&high);
+            let summary = self.summary.as_mut().unwrap();
+            summary.delete(high);
+            let last = summary.max;
+            if last.is_none() {
+                self.summary = None;
+            }
+            if self.max == Some(x) {
+                self.max = match last {
+                    Some(last) => Some(last << self.shift | self.clusters[&last].max.unwrap()),
+                    None => self.min,
+                };
+            }
+        } else if self.max == Some(x) {
+            self.max = Some(high << self.shift | self.clusters[&high].max.unwrap());
+        }
+    }
+
+    fn find_next(&self, x: i64) -> Option<i64> {
+        if self.universe == 2 {
+            return if x == 0 && self.max == Some(1) { Some(1) } else { None };
+        } else if self.min.is_some_and(|min| x < min) {
+            return self.min;
+        }
+        let (high, low) = self.split(x);
+        if let Some(cluster) = self.clusters.get(&high) {
+            if cluster.max.is_some_and(|max| low < max) {
+                return Some(high << self.shift | cluster.find_next(low).unwrap());
+            }
+        }
+        let next = self.summary.as_ref()?.find_next(high)?;
+        Some(next << self.shift | self.clusters[&next].min.unwrap())
+    }
+}
+
+// runs inserts (0), deletes (1), membership tests (2) and successor queries (3)
+// on a fresh tree, adding up the answers
+fn run_recursive(ops: &[(i64, i64)]) -> i64 {
+    let mut tree = Recursive::new(64);
+    let mut sum = 0;
+    for &(op, x) in ops {
+        match op {
+            0 => tree.insert(x),
+            1 => tree.delete(x),
+            2 => sum += tree.has(x) as i64,
+            _ => sum += tree.find_next(x).unwrap_or(-1),
+        }
+    }
+    sum
+}
+
+fn run_leaf(ops: &[(i64, i64)]) -> i64 {
+    let mut tree = VEBTree::new(64).unwrap();
+    let mut sum = 0;
+    for &(op, x) in ops {
+        match op {
+            0 => {
+                tree.insert(x);
+            }
+            1 => {
+                tree.delete(x);
+            }
+            2 => sum += tree.has(x) as i64,
+            _ => sum += tree.find_next(x).unwrap_or(-1),
+        }
+    }
+    sum
+}
+
+fn leaves() {
+    // drawn up front so that only the tree operations are timed, in runs of a
+    // thousand of the same kind so that both sides see mostly predictable jumps
+    let kinds = random(4, 2000, 4);
+    let ops: Vec<(i64, i64)> =
+        random(64, 2_000_000, 3).iter().enumerate().map(|(i, &x)| (kinds[i / 1000], x)).collect();
+    assert_eq!(run_recursive(&ops), run_leaf(&ops));
+    let naive = time(|| run_recursive(&ops));
+    let leaf = time(|| run_leaf(&ops));
+    report("leaf against clusters, 2M ops in 64", naive, leaf);
+}
+
+fn main() {
+    println!("{:<44} {:>10} {:>10} {:>7}", "", "before", "after", "");
+    from_sorted_slice();
+    leaves();
+    bulk_insert();
+    bulk_delete();
+}
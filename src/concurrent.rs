@@ -0,0 +1,148 @@
+//! A `VEBTree` that can be shared between threads.
+
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use VEBTree;
+
+/// A `VEBTree` behind a read-write lock.
+///
+/// Queries take the read lock, so any number of them can run at once; inserts
+/// and deletes take the write lock. To iterate without holding the lock, take a
+/// `snapshot` first. Only available with the `std` feature.
+#[derive(Debug)]
+pub struct ConcurrentVEBTree {
+    tree: RwLock<VEBTree>,
+}
+
+impl ConcurrentVEBTree {
+    /// Generates a new, empty tree. Fails like `VEBTree::new`.
+    pub fn new(max_elem: i64) -> Result<Self, &'static str> {
+        VEBTree::new(max_elem).map(ConcurrentVEBTree::from)
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, VEBTree> {
+        // a writer that panicked may have left the tree half-updated
+        self.tree.read().expect("tree lock poisoned")
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, VEBTree> {
+        self.tree.write().expect("tree lock poisoned")
+    }
+
+    /// Returns true if the tree contains `x`. See `VEBTree::has`.
+    pub fn has(&self, x: i64) -> bool {
+        self.read().has(x)
+    }
+
+    /// Returns the lowest element. See `VEBTree::minimum`.
+    pub fn minimum(&self) -> Option<i64> {
+        self.read().minimum()
+    }
+
+    /// Returns the highest element. See `VEBTree::maximum`.
+    pub fn maximum(&self) -> Option<i64> {
+        self.read().maximum()
+    }
+
+    /// Returns the next element above `x`. See `VEBTree::find_next`.
+    pub fn find_next(&self, x: i64) -> Option<i64> {
+        self.read().find_next(x)
+    }
+
+    /// Returns the next element below `x`. See `VEBTree::find_prev`.
+    pub fn find_prev(&self, x: i64) -> Option<i64> {
+        self.read().find_prev(x)
+    }
+
+    /// Returns the number of elements. See `VEBTree::len`.
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Returns true if the tree is empty.
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Returns the universe of the tree.
+    pub fn universe(&self) -> i64 {
+        self.read().universe()
+    }
+
+    /// Inserts `x`, returning true if it wasn't already present. See
+    /// `VEBTree::insert`.
+    pub fn insert(&self, x: i64) -> bool {
+        self.write().insert(x)
+    }
+
+    /// Removes `x`, returning true if it was present. See `VEBTree::delete`.
+    pub fn delete(&self, x: i64) -> bool {
+        self.write().delete(x)
+    }
+
+    /// Returns a copy of the tree as it is now, which can be iterated without
+    /// holding the lock.
+    pub fn snapshot(&self) -> VEBTree {
+        self.read().clone()
+    }
+
+    /// Unwraps the tree.
+    pub fn into_inner(self) -> VEBTree {
+        self.tree.into_inner().expect("tree lock poisoned")
+    }
+}
+
+impl From<VEBTree> for ConcurrentVEBTree {
+    fn from(tree: VEBTree) -> Self {
+        ConcurrentVEBTree {
+            tree: RwLock::new(tree),
+        }
+    }
+}
+
+#[test]
+fn send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<VEBTree>();
+    assert_send_sync::<ConcurrentVEBTree>();
+}
+
+#[test]
+fn readers_and_writers() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let tree = Arc::new(ConcurrentVEBTree::new(1 << 16).unwrap());
+    let writers: Vec<_> = (0..4)
+        .map(|t| {
+            let tree = Arc::clone(&tree);
+            thread::spawn(move || {
+                for i in 0..500 {
+                    tree.insert(i * 4 + t);
+                }
+            })
+        })
+        .collect();
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let tree = Arc::clone(&tree);
+            thread::spawn(move || {
+                for _ in 0..200 {
+                    // each snapshot is a consistent tree, whatever the writers do
+                    let snapshot = tree.snapshot();
+                    snapshot.check_invariants().unwrap();
+                    assert_eq!(snapshot.iter().count(), snapshot.len());
+                    if let Some(min) = tree.minimum() {
+                        assert!(tree.has(min));
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in writers.into_iter().chain(readers) {
+        handle.join().unwrap();
+    }
+    assert_eq!(tree.len(), 2000);
+    let tree = Arc::try_unwrap(tree).unwrap().into_inner();
+    assert!(tree.iter().eq(0..2000));
+}
@@ -0,0 +1,96 @@
+//! Conversions between `VEBTree` and the standard collections.
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::iter::FromIterator;
+
+use VEBTree;
+
+impl<'a> TryFrom<&'a BTreeSet<i64>> for VEBTree {
+    type Error = &'static str;
+
+    /// Builds a tree whose universe is just large enough for the set's maximum
+    /// element (or the smallest possible universe, for an empty set). Fails if
+    /// the set holds a negative value or one too large for any universe.
+    fn try_from(set: &'a BTreeSet<i64>) -> Result<Self, Self::Error> {
+        let universe = match set.iter().next_back() {
+            Some(&max) => max.checked_add(1).ok_or("universe too big")?.max(2),
+            None => 2,
+        };
+        let elements: Vec<i64> = set.iter().cloned().collect();
+        VEBTree::from_sorted_slice(&elements, universe)
+    }
+}
+
+impl<'a> From<&'a VEBTree> for BTreeSet<i64> {
+    fn from(tree: &'a VEBTree) -> Self {
+        tree.iter().collect()
+    }
+}
+
+/// Buffers the values, then builds a tree in one go whose universe is the
+/// maximum plus one rounded up to a power of two, as `with_max_element` does.
+/// Duplicates are ignored, and an empty iterator gives an empty tree of
+/// universe 2.
+///
+/// # Panics
+///
+/// Panics if a value is negative or too large for any universe.
+impl FromIterator<i64> for VEBTree {
+    fn from_iter<I: IntoIterator<Item = i64>>(iter: I) -> Self {
+        let mut elements: Vec<i64> = iter.into_iter().collect();
+        elements.sort_unstable();
+        elements.dedup();
+        let max = elements.last().map_or(0, |&max| max.max(0));
+        VEBTree::with_max_element(max)
+            .and_then(|tree| VEBTree::from_sorted_slice(&elements, tree.universe()))
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+#[test]
+fn try_from_btreeset() {
+    let set: BTreeSet<i64> = [3, 1, 4, 15, 9, 26, 5, 35, 89, 79, 32, 38].iter().cloned().collect();
+    let tree = VEBTree::try_from(&set).unwrap();
+    assert_eq!(tree.universe(), 90);
+    for x in -1..100 {
+        assert_eq!(tree.has(x), set.contains(&x));
+        assert_eq!(tree.find_next(x), set.range(x + 1..).next().cloned());
+    }
+
+    assert!(VEBTree::try_from(&BTreeSet::new()).unwrap().is_empty());
+    let negative: BTreeSet<i64> = [-1, 5].iter().cloned().collect();
+    assert!(VEBTree::try_from(&negative).is_err());
+}
+
+#[test]
+fn btreeset_round_trip() {
+    let set: BTreeSet<i64> = (0..500).map(|x| x * x % 1009).collect();
+    let tree = VEBTree::try_from(&set).unwrap();
+    assert_eq!(BTreeSet::from(&tree), set);
+    assert!(BTreeSet::from(&VEBTree::new(16).unwrap()).is_empty());
+}
+
+#[test]
+fn collect() {
+    let odd: VEBTree = (0..1000).filter(|x| x % 2 == 1).collect();
+    assert_eq!(odd.universe(), 1024);
+    assert!(odd.iter().eq((0..1000).filter(|x| x % 2 == 1)));
+
+    let tree: VEBTree = vec![40, 3, 17, 3, 64, 40, 0].into_iter().collect();
+    assert_eq!(tree.universe(), 128);
+    assert_eq!(tree.iter().collect::<Vec<_>>(), vec![0, 3, 17, 40, 64]);
+    tree.check_invariants().unwrap();
+
+    let empty: VEBTree = core::iter::empty().collect();
+    assert!(empty.is_empty());
+    assert_eq!(empty.universe(), 2);
+    assert_eq!((0..1).collect::<VEBTree>().universe(), 2);
+}
+
+#[test]
+#[should_panic(expected = "element out of range")]
+fn collect_negative() {
+    let _: VEBTree = vec![5, -1].into_iter().collect();
+}
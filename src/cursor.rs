@@ -0,0 +1,340 @@
+//! Cursors for walking a `VEBTree` in either direction.
+
+use iter::Position;
+use VEBTree;
+
+/// A cursor over the elements of a `VEBTree`.
+///
+/// Created by `VEBTree::cursor_at`. The cursor points either at an element or
+/// at a "ghost" position past both ends, from which `move_next` wraps around to
+/// the minimum and `move_prev` to the maximum. Like `Iter`, it remembers the
+/// top-level cluster it is in, so most steps only search within that cluster.
+#[derive(Debug, Clone)]
+pub struct Cursor<'a> {
+    tree: &'a VEBTree,
+    current: Option<Position<'a>>,
+}
+
+impl<'a> Cursor<'a> {
+    /// Returns the element the cursor points at, or None at the ghost position.
+    pub fn current(&self) -> Option<i64> {
+        self.current.map(|at| at.value)
+    }
+
+    /// Returns the element after the current one, without moving the cursor.
+    /// From the ghost position this is the minimum.
+    pub fn peek_next(&self) -> Option<i64> {
+        match self.current {
+            Some(at) => at.successor(self.tree).map(|next| next.value),
+            None => self.tree.minimum(),
+        }
+    }
+
+    /// Returns the element before the current one, without moving the cursor.
+    /// From the ghost position this is the maximum.
+    pub fn peek_prev(&self) -> Option<i64> {
+        match self.current {
+            Some(at) => at.predecessor(self.tree).map(|prev| prev.value),
+            None => self.tree.maximum(),
+        }
+    }
+
+    /// Moves to the next element, or to the ghost position from the maximum.
+    pub fn move_next(&mut self) {
+        let tree = self.tree;
+        self.current = match self.current {
+            Some(at) => at.successor(tree),
+            None => tree.minimum().map(|min| Position::at(tree, min)),
+        };
+    }
+
+    /// Moves to the previous element, or to the ghost position from the
+    /// minimum.
+    pub fn move_prev(&mut self) {
+        let tree = self.tree;
+        self.current = match self.current {
+            Some(at) => at.predecessor(tree),
+            None => tree.maximum().map(|max| Position::at(tree, max)),
+        };
+    }
+
+    /// Moves to the next element and returns it, as `move_next` then `current`.
+    pub fn advance(&mut self) -> Option<i64> {
+        self.move_next();
+        self.current()
+    }
+
+    /// Moves to the previous element and returns it, as `move_prev` then
+    /// `current`.
+    pub fn retreat(&mut self) -> Option<i64> {
+        self.move_prev();
+        self.current()
+    }
+
+    /// Moves to the first element `>= x` and returns it, or moves to the ghost
+    /// position and returns None if there is none.
+    pub fn seek(&mut self, x: i64) -> Option<i64> {
+        let tree = self.tree;
+        self.current = tree.ceil(x).map(|first| Position::at(tree, first));
+        self.current()
+    }
+}
+
+/// A cursor over the elements of a `VEBTree` that can remove them as it goes.
+///
+/// Created by `VEBTree::cursor_at_mut`. It moves like `Cursor`, remembering
+/// the index of the top-level cluster it is in rather than a reference to it,
+/// since the tree may change under it. Removing an element only revalidates
+/// the cluster the cursor ends up in.
+#[derive(Debug)]
+pub struct CursorMut<'a> {
+    tree: &'a mut VEBTree,
+    current: Option<Spot>,
+}
+
+// A `Position` with its cluster held by index, so it doesn't borrow the tree.
+#[derive(Debug, Clone, Copy)]
+struct Spot {
+    value: i64,
+    cluster: Option<i64>,
+}
+
+impl Spot {
+    fn of(at: Position) -> Self {
+        Spot {
+            value: at.value,
+            cluster: at.cluster_index(),
+        }
+    }
+
+    fn resume(self, tree: &VEBTree) -> Position<'_> {
+        Position::resume(tree, self.value, self.cluster)
+    }
+}
+
+impl<'a> CursorMut<'a> {
+    /// Returns the element the cursor points at, or None at the ghost position.
+    pub fn current(&self) -> Option<i64> {
+        self.current.map(|at| at.value)
+    }
+
+    /// Returns the element after the current one, without moving the cursor.
+    /// From the ghost position this is the minimum.
+    pub fn peek_next(&self) -> Option<i64> {
+        self.as_cursor().peek_next()
+    }
+
+    /// Returns the element before the current one, without moving the cursor.
+    /// From the ghost position this is the maximum.
+    pub fn peek_prev(&self) -> Option<i64> {
+        self.as_cursor().peek_prev()
+    }
+
+    /// Moves to the next element, or to the ghost position from the maximum.
+    pub fn move_next(&mut self) {
+        let mut cursor = self.as_cursor();
+        cursor.move_next();
+        self.current = cursor.current.map(Spot::of);
+    }
+
+    /// Moves to the previous element, or to the ghost position from the
+    /// minimum.
+    pub fn move_prev(&mut self) {
+        let mut cursor = self.as_cursor();
+        cursor.move_prev();
+        self.current = cursor.current.map(Spot::of);
+    }
+
+    /// Moves to the next element and returns it, as `move_next` then `current`.
+    pub fn advance(&mut self) -> Option<i64> {
+        self.move_next();
+        self.current()
+    }
+
+    /// Moves to the previous element and returns it, as `move_prev` then
+    /// `current`.
+    pub fn retreat(&mut self) -> Option<i64> {
+        self.move_prev();
+        self.current()
+    }
+
+    /// Moves to the first element `>= x` and returns it, or moves to the ghost
+    /// position and returns None if there is none.
+    pub fn seek(&mut self, x: i64) -> Option<i64> {
+        let tree = &*self.tree;
+        self.current = tree.ceil(x).map(|first| Spot::of(Position::at(tree, first)));
+        self.current()
+    }
+
+    /// Removes the current element and moves to the one after it, so the
+    /// removed element's neighbors end up on either side of the cursor. Returns
+    /// the removed element, or None (removing nothing) at the ghost position.
+    pub fn remove_current(&mut self) -> Option<i64> {
+        let x = self.current?.value;
+        let next = self.tree.delete_and_next(x);
+        // the successor's cluster may have been emptied or have given up its
+        // minimum to the tree, so look it up again
+        let tree = &*self.tree;
+        self.current = next.map(|next| Spot::of(Position::at(tree, next)));
+        Some(x)
+    }
+
+    /// Returns a read-only cursor at the same position.
+    pub fn as_cursor(&self) -> Cursor<'_> {
+        let tree = &*self.tree;
+        Cursor {
+            tree,
+            current: self.current.map(|at| at.resume(tree)),
+        }
+    }
+}
+
+impl VEBTree {
+    /// Returns a cursor at the first element `>= x`, or at the ghost position if
+    /// there is none.
+    pub fn cursor_at(&self, x: i64) -> Cursor<'_> {
+        Cursor {
+            tree: self,
+            current: self.ceil(x).map(|first| Position::at(self, first)),
+        }
+    }
+
+    /// Returns a cursor at the first element `>= x`, or at the ghost position if
+    /// there is none, that can remove elements as it goes.
+    pub fn cursor_at_mut(&mut self, x: i64) -> CursorMut<'_> {
+        let current = self.ceil(x).map(|first| Spot::of(Position::at(self, first)));
+        CursorMut {
+            tree: self,
+            current,
+        }
+    }
+}
+
+#[test]
+fn cursor_walk() {
+    let mut tree = VEBTree::new(1 << 16).unwrap();
+    let elements: Vec<i64> = (0..300).map(|i| i * 211 % 65536).collect();
+    for &x in &elements {
+        tree.insert(x);
+    }
+    let sorted: Vec<i64> = tree.iter().collect();
+
+    let mut cursor = tree.cursor_at(0);
+    let mut seen = vec![];
+    while let Some(x) = cursor.current() {
+        assert_eq!(cursor.peek_next(), tree.find_next(x));
+        assert_eq!(cursor.peek_prev(), tree.find_prev(x));
+        seen.push(x);
+        cursor.move_next();
+    }
+    assert_eq!(seen, sorted);
+    // from the ghost position both ends are one step away
+    assert_eq!(cursor.peek_next(), tree.minimum());
+    assert_eq!(cursor.peek_prev(), tree.maximum());
+    cursor.move_prev();
+    let mut seen = vec![];
+    while let Some(x) = cursor.current() {
+        seen.push(x);
+        cursor.move_prev();
+    }
+    seen.reverse();
+    assert_eq!(seen, sorted);
+
+    assert_eq!(tree.cursor_at(sorted[10] + 1).current(), Some(sorted[11]));
+    assert_eq!(tree.cursor_at(65536).current(), None);
+    assert_eq!(VEBTree::new(16).unwrap().cursor_at(0).peek_next(), None);
+}
+
+#[test]
+fn cursor_seek_advance_retreat() {
+    let tree = VEBTree::from_sorted_slice(&[2, 5, 9, 300, 4000], 1 << 12).unwrap();
+    let mut cursor = tree.cursor_at(0);
+    assert_eq!(cursor.seek(6), Some(9));
+    assert_eq!(cursor.advance(), Some(300));
+    assert_eq!(cursor.current(), Some(300));
+    assert_eq!(cursor.retreat(), Some(9));
+    assert_eq!(cursor.retreat(), Some(5));
+    assert_eq!(cursor.seek(9), Some(9));
+    assert_eq!(cursor.seek(4001), None);
+    assert_eq!(cursor.advance(), Some(2));
+    assert_eq!(cursor.retreat(), None);
+    assert_eq!(cursor.retreat(), Some(4000));
+    assert_eq!(cursor.advance(), None);
+
+    // cursors on the same tree move independently, e.g. for a merge join
+    let other = VEBTree::from_sorted_slice(&[5, 6, 300], 1 << 12).unwrap();
+    let (mut left, mut right) = (tree.cursor_at(0), other.cursor_at(0));
+    let mut behind = tree.cursor_at(0);
+    let mut common = vec![];
+    while let (Some(x), Some(y)) = (left.current(), right.current()) {
+        if x == y {
+            common.push(x);
+            left.advance();
+        } else if x < y {
+            left.seek(y);
+        } else {
+            right.seek(x);
+        }
+    }
+    assert_eq!(common, vec![5, 300]);
+    assert_eq!(behind.current(), Some(2));
+    assert_eq!(behind.advance(), Some(5));
+
+    let mut tree = tree;
+    let mut cursor = tree.cursor_at_mut(0);
+    assert_eq!(cursor.seek(10), Some(300));
+    assert_eq!(cursor.advance(), Some(4000));
+    assert_eq!(cursor.retreat(), Some(300));
+    assert_eq!(cursor.remove_current(), Some(300));
+    assert_eq!(cursor.retreat(), Some(9));
+}
+
+#[test]
+fn cursor_remove_every_other() {
+    let mut tree = VEBTree::new(1000).unwrap();
+    for x in 0..200 {
+        tree.insert(x * 5);
+    }
+    {
+        let mut cursor = tree.cursor_at_mut(0);
+        while cursor.current().is_some() {
+            let removed = cursor.remove_current().unwrap();
+            // the cursor sits between the removed element's neighbors
+            assert_eq!(cursor.current(), if removed < 995 { Some(removed + 5) } else { None });
+            assert_eq!(cursor.peek_prev(), if removed > 0 { Some(removed - 5) } else { None });
+            assert_eq!(cursor.as_cursor().current(), cursor.current());
+            cursor.move_next();
+        }
+        assert_eq!(cursor.remove_current(), None);
+    }
+    assert!(tree.iter().eq((0..100).map(|x| x * 10 + 5)));
+    tree.check_invariants().unwrap();
+}
+
+#[test]
+fn cursor_mut_matches_btreeset() {
+    for &universe in &[64, 4096, 1 << 20] {
+        let (mut tree, oracle) = ::random_tree(universe, 1500, 23);
+        {
+            // walk down from the top, removing every third element
+            let mut cursor = tree.cursor_at_mut(universe);
+            cursor.move_prev();
+            let mut expected = oracle.iter().rev();
+            let mut i = 0;
+            while let Some(x) = cursor.current() {
+                assert_eq!(Some(&x), expected.next());
+                if i % 3 == 0 {
+                    assert_eq!(cursor.remove_current(), Some(x));
+                    assert_eq!(cursor.current(), oracle.range(x + 1..).next().cloned());
+                    assert_eq!(cursor.peek_prev(), oracle.range(..x).next_back().cloned());
+                }
+                cursor.move_prev();
+                i += 1;
+            }
+            assert_eq!(expected.next(), None);
+        }
+        let kept = oracle.iter().rev().enumerate().filter(|&(i, _)| i % 3 != 0);
+        assert!(tree.iter().rev().eq(kept.map(|(_, &x)| x)));
+        tree.check_invariants().unwrap();
+    }
+}
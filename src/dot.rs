@@ -0,0 +1,75 @@
+//! GraphViz export of a tree's internal structure.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use VEBTree;
+
+impl VEBTree {
+    /// Renders the structure of this tree in GraphViz DOT format, e.g. for
+    /// `dot -Tpng`. Each node is labeled with its universe, min and max, with
+    /// edges to its summary and to each allocated cluster.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph veb {\n");
+        self.write_dot(&mut out, &mut 0);
+        out.push_str("}\n");
+        out
+    }
+
+    fn write_dot(&self, out: &mut String, next_id: &mut usize) -> usize {
+        let id = *next_id;
+        *next_id += 1;
+        if self.is_empty() {
+            writeln!(out, "    n{} [label=\"u={} empty\"];", id, self.universe).unwrap();
+        } else {
+            writeln!(
+                out,
+                "    n{} [label=\"u={} min={} max={}\"];",
+                id, self.universe, self.min, self.max
+            )
+            .unwrap();
+        }
+        if let Some(ref summary) = self.summary {
+            let child = summary.write_dot(out, next_id);
+            writeln!(out, "    n{} -> n{} [label=\"summary\"];", id, child).unwrap();
+        }
+        let mut indices: Vec<_> = self.children.keys().cloned().collect();
+        indices.sort();
+        for idx in indices {
+            let child = self.children[&idx].write_dot(out, next_id);
+            writeln!(out, "    n{} -> n{} [label=\"{}\"];", id, child, idx).unwrap();
+        }
+        id
+    }
+}
+
+#[test]
+fn to_dot() {
+    let mut tree = VEBTree::new(16).unwrap();
+    for &x in &[1, 6, 7, 12] {
+        tree.insert(x);
+    }
+    let dot = tree.to_dot();
+    assert!(dot.starts_with("digraph veb {"));
+    assert_eq!(dot.matches("[label=\"u=").count(), tree.node_count());
+    assert_eq!(dot.matches(" -> ").count(), tree.node_count() - 1);
+    assert!(dot.contains("u=16 min=1 max=12"));
+
+    let empty = VEBTree::new(16).unwrap().to_dot();
+    assert!(empty.contains("u=16 empty"));
+}
+
+#[test]
+fn to_dot_edges() {
+    let mut tree = VEBTree::new(256).unwrap();
+    assert_eq!(tree.to_dot(), "digraph veb {\n    n0 [label=\"u=256 empty\"];\n}\n");
+    tree.insert(1);
+    tree.insert(13 * 16);
+    let dot = tree.to_dot();
+    assert!(dot.contains("digraph") && dot.contains("min="));
+    // one edge to the summary and one to the only cluster, labeled with its index
+    assert_eq!(dot.matches("[label=\"summary\"]").count(), 1);
+    assert_eq!(dot.matches("[label=\"13\"]").count(), 1);
+    assert_eq!(dot.matches(" -> ").count(), 2);
+}
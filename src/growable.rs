@@ -0,0 +1,86 @@
+//! A `VEBTree` that grows its universe to fit whatever is inserted.
+
+use alloc::vec::Vec;
+
+use VEBTree;
+
+/// A `VEBTree` whose universe grows on demand.
+///
+/// Inserting a value beyond the current universe rebuilds the tree over the
+/// next power of two above it, keeping every element. A rebuild takes time
+/// linear in the number of elements, but since the universe at least doubles
+/// each time, a steadily growing tree rebuilds only O(log(max)) times. Reads
+/// go through `tree`.
+#[derive(Debug, Clone)]
+pub struct GrowableVEBTree {
+    tree: VEBTree,
+}
+
+impl GrowableVEBTree {
+    /// Generates a new, empty tree with the given starting universe. Fails like
+    /// `VEBTree::new`.
+    pub fn new(max_elem: i64) -> Result<Self, &'static str> {
+        VEBTree::new(max_elem).map(GrowableVEBTree::from)
+    }
+
+    /// Returns the tree as it stands, for queries.
+    pub fn tree(&self) -> &VEBTree {
+        &self.tree
+    }
+
+    /// Inserts `x`, returning whether it wasn't already present, and first
+    /// growing the universe if `x` lies beyond it. Fails, leaving the tree
+    /// untouched, if `x` is negative or no universe could hold it.
+    pub fn insert(&mut self, x: i64) -> Result<bool, &'static str> {
+        if x >= self.tree.universe() {
+            let universe = VEBTree::with_max_element(x)?.universe();
+            let elements: Vec<i64> = self.tree.iter().collect();
+            self.tree = VEBTree::build_sorted(universe, &elements);
+        } else if x < 0 {
+            return Err("element out of range");
+        }
+        Ok(self.tree.insert(x))
+    }
+
+    /// Removes `x`, returning true if it was present. The universe never
+    /// shrinks. See `VEBTree::delete`.
+    pub fn delete(&mut self, x: i64) -> bool {
+        self.tree.delete(x)
+    }
+
+    /// Unwraps the tree.
+    pub fn into_inner(self) -> VEBTree {
+        self.tree
+    }
+}
+
+impl From<VEBTree> for GrowableVEBTree {
+    fn from(tree: VEBTree) -> Self {
+        GrowableVEBTree { tree }
+    }
+}
+
+#[test]
+fn grows_to_fit() {
+    let mut tree = GrowableVEBTree::new(16).unwrap();
+    let mut universes = vec![tree.tree().universe()];
+    for i in 0..2000 {
+        assert_eq!(tree.insert(i * i), Ok(true));
+        if tree.tree().universe() != *universes.last().unwrap() {
+            universes.push(tree.tree().universe());
+        }
+    }
+    // 1999^2 needs a universe of 2^22, reached in several steps
+    assert_eq!(tree.tree().universe(), 1 << 22);
+    assert!(universes.len() > 3);
+    assert!(universes.windows(2).all(|pair| pair[0] < pair[1]));
+    tree.tree().check_invariants().unwrap();
+    assert!(tree.tree().iter().eq((0..2000).map(|i| i * i)));
+
+    assert_eq!(tree.insert(4), Ok(false));
+    assert!(tree.delete(4));
+    assert!(tree.insert(-1).is_err());
+    assert!(tree.insert(i64::MAX).is_err());
+    assert_eq!(tree.tree().universe(), 1 << 22);
+    assert_eq!(tree.into_inner().len(), 1999);
+}
@@ -0,0 +1,802 @@
+//! Iterators over the elements of a `VEBTree`.
+
+use core::ops::{Bound, RangeBounds};
+
+use VEBTree;
+
+/// Splits a range into the value to search after (`None` meaning "start at the
+/// minimum") and its upper bound.
+pub(crate) fn range_bounds<R: RangeBounds<i64>>(range: &R) -> (Option<i64>, Bound<i64>) {
+    let after = match range.start_bound() {
+        Bound::Included(&x) => x.checked_sub(1),
+        Bound::Excluded(&x) => Some(x),
+        Bound::Unbounded => None,
+    };
+    (after, range.end_bound().cloned())
+}
+
+fn below(x: i64, end: Bound<i64>) -> bool {
+    match end {
+        Bound::Included(e) => x <= e,
+        Bound::Excluded(e) => x < e,
+        Bound::Unbounded => true,
+    }
+}
+
+// a stored value, and the top-level cluster holding it (None for the minimum,
+// which isn't stored in a cluster, or when the tree has no clusters at all).
+// Stepping from a position only searches within its cluster until that runs out.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Position<'a> {
+    pub(crate) value: i64,
+    cluster: Option<(i64, &'a VEBTree)>,
+}
+
+impl<'a> Position<'a> {
+    pub(crate) fn at(tree: &'a VEBTree, value: i64) -> Self {
+        let cluster = if value == tree.min || tree.summary.is_none() {
+            None
+        } else {
+            let idx = tree.high(value);
+            Some((idx, &tree.children[&idx]))
+        };
+        Position { value, cluster }
+    }
+
+    // Rebuilds a position from its value and the index of its cluster, as
+    // returned by `cluster_index`, looking the cluster up again.
+    pub(crate) fn resume(tree: &'a VEBTree, value: i64, cluster: Option<i64>) -> Self {
+        Position {
+            value,
+            cluster: cluster.map(|idx| (idx, &tree.children[&idx])),
+        }
+    }
+
+    pub(crate) fn cluster_index(self) -> Option<i64> {
+        self.cluster.map(|(idx, _)| idx)
+    }
+
+    fn enter(tree: &'a VEBTree, idx: i64, first: bool) -> Self {
+        let cluster = &tree.children[&idx];
+        let low = if first { cluster.min } else { cluster.max };
+        Position {
+            value: tree.index(idx, low),
+            cluster: Some((idx, cluster)),
+        }
+    }
+
+    pub(crate) fn successor(self, tree: &'a VEBTree) -> Option<Self> {
+        match self.cluster {
+            Some((idx, cluster)) => match cluster.find_next(tree.low(self.value)) {
+                Some(low) => Some(Position {
+                    value: tree.index(idx, low),
+                    cluster: self.cluster,
+                }),
+                None => {
+                    let next = tree.summary.as_ref().unwrap().find_next(idx)?;
+                    Some(Position::enter(tree, next, true))
+                }
+            },
+            None => match tree.summary {
+                Some(ref summary) => Some(Position::enter(tree, summary.min, true)),
+                None => tree.find_next(self.value).map(|value| Position {
+                    value,
+                    cluster: None,
+                }),
+            },
+        }
+    }
+
+    pub(crate) fn predecessor(self, tree: &'a VEBTree) -> Option<Self> {
+        match self.cluster {
+            Some((idx, cluster)) => match cluster.find_prev(tree.low(self.value)) {
+                Some(low) => Some(Position {
+                    value: tree.index(idx, low),
+                    cluster: self.cluster,
+                }),
+                None => match tree.summary.as_ref().unwrap().find_prev(idx) {
+                    Some(prev) => Some(Position::enter(tree, prev, false)),
+                    // the minimum is all that's left below the clusters
+                    None => Some(Position {
+                        value: tree.min,
+                        cluster: None,
+                    }),
+                },
+            },
+            None => match tree.summary {
+                Some(_) => None,
+                None => tree.find_prev(self.value).map(|value| Position {
+                    value,
+                    cluster: None,
+                }),
+            },
+        }
+    }
+}
+
+// A bidirectional walk over the elements between two stored values, holding
+// the next position due from each end.
+#[derive(Debug, Clone)]
+struct Walk<'a> {
+    tree: &'a VEBTree,
+    front: Option<Position<'a>>,
+    back: Option<Position<'a>>,
+}
+
+impl<'a> Walk<'a> {
+    fn new(tree: &'a VEBTree, first: Option<i64>, last: Option<i64>) -> Self {
+        let mut walk = Walk {
+            tree,
+            front: None,
+            back: None,
+        };
+        if let (Some(first), Some(last)) = (first, last) {
+            if first <= last {
+                walk.front = Some(Position::at(tree, first));
+                walk.back = Some(Position::at(tree, last));
+            }
+        }
+        walk
+    }
+
+    fn next(&mut self) -> Option<i64> {
+        let front = self.front?;
+        if front.value == self.back.unwrap().value {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.front = front.successor(self.tree);
+        }
+        Some(front.value)
+    }
+
+    fn next_back(&mut self) -> Option<i64> {
+        let back = self.back?;
+        if back.value == self.front.unwrap().value {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.back = back.predecessor(self.tree);
+        }
+        Some(back.value)
+    }
+}
+
+/// An iterator over the elements of a `VEBTree` in ascending order.
+///
+/// Created by `VEBTree::iter`. Each end of the iterator remembers the
+/// top-level cluster it is in, so most steps only search within that cluster
+/// rather than descending from the root. The iterator is double-ended, and
+/// stops once the two ends meet. It knows exactly how many elements are left.
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    walk: Walk<'a>,
+    remaining: usize,
+}
+
+impl<'a> Iter<'a> {
+    pub(crate) fn new(tree: &'a VEBTree) -> Self {
+        Iter {
+            walk: Walk::new(tree, tree.minimum(), tree.maximum()),
+            remaining: tree.len(),
+        }
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        let x = self.walk.next()?;
+        self.remaining -= 1;
+        Some(x)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> DoubleEndedIterator for Iter<'a> {
+    fn next_back(&mut self) -> Option<i64> {
+        let x = self.walk.next_back()?;
+        self.remaining -= 1;
+        Some(x)
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+/// An iterator over the elements of a `VEBTree` within a range, in ascending
+/// order.
+///
+/// Created by `VEBTree::range`. Both ends are found with a single descent, and
+/// the iterator is double-ended like `Iter`.
+#[derive(Debug, Clone)]
+pub struct Range<'a> {
+    walk: Walk<'a>,
+}
+
+impl<'a> Range<'a> {
+    pub(crate) fn new<R: RangeBounds<i64>>(tree: &'a VEBTree, range: R) -> Self {
+        match (range.start_bound(), range.end_bound()) {
+            (Bound::Excluded(s), Bound::Excluded(e)) if s == e => {
+                panic!("range start and end are equal and excluded")
+            }
+            (Bound::Included(s), Bound::Included(e))
+            | (Bound::Included(s), Bound::Excluded(e))
+            | (Bound::Excluded(s), Bound::Included(e))
+            | (Bound::Excluded(s), Bound::Excluded(e))
+                if s > e =>
+            {
+                panic!("range start is greater than range end")
+            }
+            _ => {}
+        }
+        let first = match range.start_bound() {
+            Bound::Included(&s) => tree.ceil(s),
+            Bound::Excluded(&s) => tree.find_next(s),
+            Bound::Unbounded => tree.minimum(),
+        };
+        let last = match range.end_bound() {
+            Bound::Included(&e) => tree.floor(e),
+            Bound::Excluded(&e) => tree.find_prev(e),
+            Bound::Unbounded => tree.maximum(),
+        };
+        Range {
+            walk: Walk::new(tree, first, last),
+        }
+    }
+}
+
+impl<'a> Iterator for Range<'a> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        self.walk.next()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Range<'a> {
+    fn next_back(&mut self) -> Option<i64> {
+        self.walk.next_back()
+    }
+}
+
+/// Iterates by value, like `VEBTree::iter`.
+impl<'a> IntoIterator for &'a VEBTree {
+    type Item = i64;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// An owning iterator over the elements of a `VEBTree` in ascending order.
+///
+/// Created by `VEBTree::into_iter`. Each step removes the minimum (or the
+/// maximum, from the back), so clusters are freed as soon as they have been
+/// walked.
+#[derive(Debug, Clone)]
+pub struct IntoIter {
+    tree: VEBTree,
+}
+
+impl Iterator for IntoIter {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        self.tree.pop_min()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.tree.len(), Some(self.tree.len()))
+    }
+}
+
+impl DoubleEndedIterator for IntoIter {
+    fn next_back(&mut self) -> Option<i64> {
+        self.tree.pop_max()
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+impl IntoIterator for VEBTree {
+    type Item = i64;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter { tree: self }
+    }
+}
+
+/// A draining iterator over the elements of a `VEBTree` within a range.
+///
+/// Created by `VEBTree::drain_range`. Elements are yielded in ascending order
+/// and removed from the tree as they are yielded; if the iterator is dropped
+/// early, the rest of the range is removed anyway.
+#[derive(Debug)]
+pub struct DrainRange<'a> {
+    tree: &'a mut VEBTree,
+    // the next element to remove, if it's still within the range
+    next: Option<i64>,
+    end: Bound<i64>,
+}
+
+impl<'a> DrainRange<'a> {
+    pub(crate) fn new<R: RangeBounds<i64>>(tree: &'a mut VEBTree, range: R) -> Self {
+        let (after, end) = range_bounds(&range);
+        let next = match after {
+            Some(x) => tree.find_next(x),
+            None => tree.minimum(),
+        };
+        DrainRange { tree, next, end }
+    }
+}
+
+impl<'a> Iterator for DrainRange<'a> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        match self.next {
+            Some(x) if below(x, self.end) => {
+                // removing x finds its successor on the same descent
+                self.next = self.tree.delete_and_next(x);
+                Some(x)
+            }
+            _ => {
+                self.next = None;
+                None
+            }
+        }
+    }
+}
+
+impl<'a> Drop for DrainRange<'a> {
+    fn drop(&mut self) {
+        while self.next().is_some() {}
+    }
+}
+
+/// A draining iterator over all the elements of a `VEBTree`, in ascending
+/// order.
+///
+/// Created by `VEBTree::drain`. The tree is left empty once the iterator is
+/// dropped, even if it wasn't run to completion.
+#[derive(Debug)]
+pub struct VEBDrain<'a> {
+    tree: &'a mut VEBTree,
+}
+
+impl<'a> VEBDrain<'a> {
+    pub(crate) fn new(tree: &'a mut VEBTree) -> Self {
+        VEBDrain { tree }
+    }
+}
+
+impl<'a> Iterator for VEBDrain<'a> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        self.tree.pop_min()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.tree.len(), Some(self.tree.len()))
+    }
+}
+
+impl<'a> ExactSizeIterator for VEBDrain<'a> {}
+
+impl<'a> Drop for VEBDrain<'a> {
+    fn drop(&mut self) {
+        // whatever is left can be discarded wholesale rather than deleted one
+        // element at a time
+        self.tree.min = self.tree.universe;
+        self.tree.max = -1;
+        self.tree.children.clear();
+        self.tree.summary = None;
+        self.tree.bits = 0;
+        self.tree.len = 0;
+    }
+}
+
+/// An iterator over the values within a range that are *not* stored in a
+/// `VEBTree`, in ascending order.
+///
+/// Created by `VEBTree::gaps`. The range is clipped to the tree's universe.
+/// Runs of free values are yielded without touching the tree; only stepping
+/// over a stored element costs a successor query.
+#[derive(Debug, Clone)]
+pub struct Gaps<'a> {
+    tree: &'a VEBTree,
+    next: i64,
+    end: i64,
+    // the first stored element >= next
+    blocked: Option<i64>,
+}
+
+impl<'a> Gaps<'a> {
+    pub(crate) fn new<R: RangeBounds<i64>>(tree: &'a VEBTree, range: R) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&e) => e.saturating_add(1),
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => tree.universe(),
+        };
+        let next = start.max(0);
+        Gaps {
+            tree,
+            next,
+            end: end.min(tree.universe()),
+            blocked: tree.ceil(next),
+        }
+    }
+}
+
+impl<'a> Iterator for Gaps<'a> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        while self.next < self.end {
+            let x = self.next;
+            self.next += 1;
+            if self.blocked == Some(x) {
+                self.blocked = self.tree.find_next(x);
+            } else {
+                return Some(x);
+            }
+        }
+        None
+    }
+}
+
+/// An iterator over the pairs of consecutive elements of a `VEBTree`, in
+/// ascending order: `(1, 4)` then `(4, 10)` for `{1, 4, 10}`.
+///
+/// Created by `VEBTree::gap_pairs`. It walks the tree with an ordinary `Iter`,
+/// holding on to the previous element.
+#[derive(Debug, Clone)]
+pub struct GapPairs<'a> {
+    iter: Iter<'a>,
+    prev: Option<i64>,
+}
+
+impl<'a> GapPairs<'a> {
+    pub(crate) fn new(tree: &'a VEBTree) -> Self {
+        let mut iter = tree.iter();
+        let prev = iter.next();
+        GapPairs { iter, prev }
+    }
+}
+
+impl<'a> Iterator for GapPairs<'a> {
+    type Item = (i64, i64);
+
+    fn next(&mut self) -> Option<(i64, i64)> {
+        let next = self.iter.next()?;
+        let prev = self.prev.replace(next)?;
+        Some((prev, next))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.iter.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for GapPairs<'a> {}
+
+#[test]
+fn drain_range_matches_btreeset() {
+    use std::collections::BTreeSet;
+
+    let mut tree = VEBTree::new(256).unwrap();
+    let mut oracle = BTreeSet::new();
+    for i in 0..100 {
+        let x = (i * 37 + 11) % 256;
+        tree.insert(x);
+        oracle.insert(x);
+    }
+
+    let drained: Vec<i64> = tree.drain_range(40..=150).collect();
+    let expected: Vec<i64> = oracle.range(40..=150).cloned().collect();
+    assert_eq!(drained, expected);
+    for x in expected {
+        oracle.remove(&x);
+    }
+    for x in 0..256 {
+        assert_eq!(tree.has(x), oracle.contains(&x));
+    }
+
+    let drained: Vec<i64> = tree.drain_range(..40).collect();
+    let expected: Vec<i64> = oracle.range(..40).cloned().collect();
+    assert_eq!(drained, expected);
+    assert_eq!(tree.minimum(), oracle.range(40..).next().cloned());
+
+    for &universe in &[64, 5000, 1 << 20] {
+        let (mut tree, mut oracle) = ::random_tree(universe, 2000, 19);
+        let thirds = universe / 3;
+        for &(start, end) in &[(thirds, 2 * thirds), (0, thirds / 2), (thirds, universe)] {
+            let drained: Vec<i64> = tree.drain_range(start..end).collect();
+            let expected: Vec<i64> = oracle.range(start..end).cloned().collect();
+            assert_eq!(drained, expected);
+            for x in expected {
+                oracle.remove(&x);
+            }
+            tree.check_invariants().unwrap();
+            assert!(tree.iter().eq(oracle.iter().cloned()));
+        }
+    }
+}
+
+#[test]
+fn drain_range_early_drop() {
+    let mut tree = VEBTree::new(16).unwrap();
+    for x in 2..12 {
+        tree.insert(x);
+    }
+    assert_eq!(tree.drain_range(4..8).next(), Some(4));
+    for x in 2..12 {
+        assert_eq!(tree.has(x), !(4..8).contains(&x));
+    }
+}
+
+#[test]
+fn drain_range_empty_range() {
+    let mut tree = VEBTree::new(16).unwrap();
+    tree.insert(3);
+    tree.insert(9);
+    assert_eq!(tree.drain_range(4..4).count(), 0);
+    assert_eq!(tree.drain_range(10..).count(), 0);
+    assert!(tree.has(3));
+    assert!(tree.has(9));
+}
+
+#[test]
+fn iter_matches_btreeset() {
+    use std::collections::BTreeSet;
+
+    for &universe in &[2, 3, 16, 100, 256, 65536] {
+        let mut tree = VEBTree::new(universe).unwrap();
+        let mut oracle = BTreeSet::new();
+        assert_eq!(tree.iter().count(), 0);
+        tree.insert(universe / 2);
+        oracle.insert(universe / 2);
+        assert_eq!(tree.iter().collect::<Vec<_>>(), vec![universe / 2]);
+        for i in 0..300 {
+            let x = (i * 7919 + 13) % universe;
+            tree.insert(x);
+            oracle.insert(x);
+        }
+        assert!(tree.iter().eq(oracle.iter().cloned()));
+    }
+}
+
+#[test]
+fn into_iter() {
+    let mut tree = VEBTree::new(1000).unwrap();
+    for i in 0..200 {
+        tree.insert(i * 37 % 1000);
+    }
+    let expected: Vec<i64> = tree.iter().collect();
+    assert_eq!(tree.clone().into_iter().collect::<Vec<_>>(), expected);
+    let doubled: Vec<i64> = tree.clone().into_iter().filter(|x| x % 2 == 0).map(|x| x * 2).collect();
+    assert_eq!(doubled, expected.iter().filter(|&x| x % 2 == 0).map(|x| x * 2).collect::<Vec<_>>());
+    let mut count = 0;
+    for x in tree {
+        assert_eq!(x, expected[count]);
+        count += 1;
+    }
+    assert_eq!(count, expected.len());
+}
+
+#[test]
+fn into_iter_borrowed() {
+    let mut tree = VEBTree::new(64).unwrap();
+    let mut other = VEBTree::new(1000).unwrap();
+    for x in 0..10 {
+        tree.insert(x * 3);
+        other.insert(x * 3 + 1);
+    }
+    let mut seen = vec![];
+    for x in &tree {
+        seen.push(x);
+    }
+    assert_eq!(seen, tree.iter().collect::<Vec<_>>());
+    assert!((&tree).into_iter().zip(&other).all(|(a, b)| b == a + 1));
+}
+
+#[test]
+fn into_iter_generic() {
+    fn collect<I: IntoIterator<Item = i64>>(elements: I) -> Vec<i64> {
+        elements.into_iter().collect()
+    }
+
+    let mut tree = VEBTree::new(1 << 16).unwrap();
+    for &x in &[40000, 3, 512, 70] {
+        tree.insert(x);
+    }
+    assert_eq!(collect(&tree), vec![3, 70, 512, 40000]);
+    // borrowing left the tree usable
+    assert_eq!(tree.maximum(), Some(40000));
+    assert_eq!(collect(tree), vec![3, 70, 512, 40000]);
+}
+
+#[test]
+fn iter_rev() {
+    use std::collections::BTreeSet;
+
+    for &universe in &[2, 3, 16, 100, 65536] {
+        let mut tree = VEBTree::new(universe).unwrap();
+        let mut oracle = BTreeSet::new();
+        assert_eq!(tree.iter().next_back(), None);
+        for i in 0..300 {
+            let x = (i * 7919 + 13) % universe;
+            tree.insert(x);
+            oracle.insert(x);
+        }
+        assert!(tree.iter().rev().eq(oracle.iter().rev().cloned()));
+        assert!(tree.clone().into_iter().rev().eq(oracle.iter().rev().cloned()));
+
+        // alternate ends until they meet, in every phase
+        for skew in 0..3 {
+            let mut iter = tree.iter();
+            let mut oracle_iter = oracle.iter().cloned();
+            for step in 0.. {
+                let (got, expected) = if step % 3 == skew {
+                    (iter.next_back(), oracle_iter.next_back())
+                } else {
+                    (iter.next(), oracle_iter.next())
+                };
+                assert_eq!(got, expected);
+                if got.is_none() {
+                    break;
+                }
+            }
+            assert_eq!(iter.next(), None);
+            assert_eq!(iter.next_back(), None);
+        }
+    }
+}
+
+#[test]
+fn range_matches_btreeset() {
+    use std::collections::BTreeSet;
+    use std::ops::Bound::*;
+
+    let mut tree = VEBTree::new(1000).unwrap();
+    let mut oracle = BTreeSet::new();
+    for i in 0..150 {
+        let x = (i * 7919 + 13) % 1000;
+        tree.insert(x);
+        oracle.insert(x);
+    }
+    let points = [-5, 0, 13, 14, 500, 998, 999, 1000, 2000];
+    let bound = |kind: usize, x: i64| match kind {
+        0 => Included(x),
+        1 => Excluded(x),
+        _ => Unbounded,
+    };
+    for &s in &points {
+        assert!(tree.iter_from(s).eq(oracle.range(s..).cloned()));
+        for &e in &points {
+            for kinds in 0..9 {
+                let range = (bound(kinds / 3, s), bound(kinds % 3, e));
+                let valid = match range {
+                    (Excluded(s), Excluded(e)) => s < e,
+                    (Included(s), Included(e))
+                    | (Included(s), Excluded(e))
+                    | (Excluded(s), Included(e)) => s <= e,
+                    _ => true,
+                };
+                if valid {
+                    assert!(tree.range(range).eq(oracle.range(range).cloned()));
+                    assert!(tree.range(range).rev().eq(oracle.range(range).rev().cloned()));
+                }
+            }
+        }
+    }
+    assert_eq!(tree.range(14..14).count(), 0);
+    assert_eq!(VEBTree::new(16).unwrap().range(..).count(), 0);
+}
+
+#[test]
+#[should_panic]
+fn range_inverted() {
+    use std::ops::Bound::Included;
+
+    VEBTree::new(16).unwrap().range((Included(5), Included(3)));
+}
+
+#[test]
+#[should_panic]
+fn range_equal_excluded() {
+    use std::ops::Bound::Excluded;
+
+    VEBTree::new(16).unwrap().range((Excluded(3), Excluded(3)));
+}
+
+#[test]
+fn drain() {
+    let mut tree = VEBTree::new(1000).unwrap();
+    for x in &[5, 999, 0, 64, 500] {
+        tree.insert(*x);
+    }
+    let drained: Vec<i64> = tree.drain().collect();
+    assert_eq!(drained, vec![0, 5, 64, 500, 999]);
+    assert!(tree.is_empty());
+
+    for x in 0..100 {
+        tree.insert(x * 3);
+    }
+    for x in tree.drain() {
+        if x > 30 {
+            break;
+        }
+    }
+    assert!(tree.is_empty());
+    assert_eq!(tree.node_count(), 1);
+    tree.check_invariants().unwrap();
+    tree.insert(7);
+    assert_eq!(tree.iter().collect::<Vec<_>>(), vec![7]);
+}
+
+#[test]
+fn exact_size() {
+    let mut tree = VEBTree::new(1 << 20).unwrap();
+    for x in 0..500 {
+        tree.insert(x * 997);
+    }
+    let mut iter = tree.iter();
+    assert_eq!(iter.len(), 500);
+    iter.next();
+    iter.next_back();
+    assert_eq!(iter.len(), 498);
+    assert_eq!(iter.by_ref().count(), 498);
+    assert_eq!(iter.len(), 0);
+    assert_eq!(tree.iter().collect::<Vec<_>>().capacity(), 500);
+
+    let mut into_iter = tree.clone().into_iter();
+    into_iter.next_back();
+    assert_eq!(into_iter.len(), 499);
+    let mut drain = tree.drain();
+    drain.next();
+    assert_eq!(drain.len(), 499);
+}
+
+#[test]
+fn gaps() {
+    let (tree, _) = ::random_tree(1000, 500, 5);
+    for &(s, e) in &[(0, 1000), (100, 400), (-50, 30), (990, 2000), (500, 500), (700, 600)] {
+        let free: Vec<i64> = (s.max(0)..e.min(1000)).filter(|&x| !tree.has(x)).collect();
+        assert_eq!(tree.gaps(s..e).collect::<Vec<_>>(), free);
+    }
+    assert_eq!(tree.gaps(..).count(), 1000 - tree.len());
+    assert!(tree.gaps(..=0).eq((0..1).filter(|&x| !tree.has(x))));
+    assert!(VEBTree::new(16).unwrap().gaps(..).eq(0..16));
+}
+
+#[test]
+fn gap_pairs() {
+    let tree = VEBTree::from_sorted_slice(&[1, 4, 10], 16).unwrap();
+    assert_eq!(tree.gap_pairs().collect::<Vec<_>>(), vec![(1, 4), (4, 10)]);
+    assert_eq!(tree.gap_pairs().len(), 2);
+    assert_eq!(VEBTree::from_sorted_slice(&[7], 16).unwrap().gap_pairs().next(), None);
+    assert_eq!(VEBTree::new(16).unwrap().gap_pairs().len(), 0);
+
+    let mut tree = VEBTree::new(1 << 20).unwrap();
+    for i in 0..2000 {
+        tree.insert(i * 7919 % (1 << 20));
+    }
+    let elements: Vec<i64> = tree.iter().collect();
+    let pairs: Vec<(i64, i64)> = elements.windows(2).map(|pair| (pair[0], pair[1])).collect();
+    assert!(tree.gap_pairs().eq(pairs.into_iter()));
+    assert!(tree.gap_pairs().all(|(x, next)| tree.find_next(x) == Some(next)));
+}
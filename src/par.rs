@@ -0,0 +1,97 @@
+//! Parallel iteration with rayon, behind the `rayon` feature.
+
+use rayon::iter::plumbing::UnindexedConsumer;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use VEBTree;
+
+// clusters holding more elements than this are split into their own clusters
+// rather than walked as one task, so a skewed tree still spreads out
+const SPLIT_LEN: usize = 1 << 12;
+
+/// A parallel iterator over the elements of a `VEBTree`, in no particular
+/// order.
+///
+/// Created by `par_iter` (via rayon's `IntoParallelRefIterator`) or
+/// `into_par_iter` on `&VEBTree`. The tree is divided at cluster boundaries, so
+/// each task walks a disjoint set of clusters with an ordinary `Iter`; clusters
+/// that are too large on their own are divided further in the same way.
+#[derive(Debug, Clone, Copy)]
+pub struct ParIter<'a> {
+    tree: &'a VEBTree,
+}
+
+// sorts the clusters of a node into single elements (the minimums of the nodes
+// that get divided) and whole clusters to be walked, each with the offset of its
+// first value
+fn divide<'a>(
+    node: &'a VEBTree,
+    offset: i64,
+    loose: &mut Vec<i64>,
+    chunks: &mut Vec<(i64, &'a VEBTree)>,
+) {
+    let summary = match node.summary {
+        Some(ref summary) => summary,
+        None => {
+            // at most a leaf's worth of elements, or just the minimum
+            loose.extend(node.iter().map(|x| offset + x));
+            return;
+        }
+    };
+    loose.push(offset + node.min);
+    for idx in summary.iter() {
+        let cluster = &node.children[&idx];
+        let cluster_offset = offset + node.index(idx, 0);
+        if cluster.len() > SPLIT_LEN {
+            divide(cluster, cluster_offset, loose, chunks);
+        } else {
+            chunks.push((cluster_offset, cluster));
+        }
+    }
+}
+
+impl<'a> ParallelIterator for ParIter<'a> {
+    type Item = i64;
+
+    fn drive_unindexed<C: UnindexedConsumer<i64>>(self, consumer: C) -> C::Result {
+        let mut loose = vec![];
+        let mut chunks = vec![];
+        divide(self.tree, 0, &mut loose, &mut chunks);
+        loose
+            .into_par_iter()
+            .chain(
+                chunks
+                    .into_par_iter()
+                    .flat_map_iter(|(offset, cluster)| cluster.iter().map(move |x| offset + x)),
+            )
+            .drive_unindexed(consumer)
+    }
+}
+
+impl<'a> IntoParallelIterator for &'a VEBTree {
+    type Iter = ParIter<'a>;
+    type Item = i64;
+
+    fn into_par_iter(self) -> ParIter<'a> {
+        ParIter { tree: self }
+    }
+}
+
+#[test]
+fn par_iter_matches_iter() {
+    use rayon::iter::IntoParallelRefIterator;
+
+    let (uniform, _) = ::random_tree(1 << 32, 50000, 11);
+    let mut skewed = VEBTree::new(1 << 32).unwrap();
+    for (i, x) in uniform.iter().enumerate() {
+        // nearly everything in one top-level cluster, which has to be divided
+        skewed.insert(if i % 100 == 0 { x } else { i as i64 });
+    }
+    let small = VEBTree::from_sorted_slice(&[3, 9], 64).unwrap();
+    for tree in &[uniform, skewed, small, VEBTree::new(50).unwrap()] {
+        let mut elements: Vec<i64> = tree.par_iter().collect();
+        elements.sort_unstable();
+        assert_eq!(elements, tree.iter().collect::<Vec<_>>());
+        assert_eq!(tree.par_iter().sum::<i64>(), tree.iter().sum::<i64>());
+    }
+}
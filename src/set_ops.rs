@@ -0,0 +1,1142 @@
+//! Set operations between two `VEBTree`s.
+
+use core::cmp::{self, Ordering};
+use core::iter::Peekable;
+use core::ops::{BitAnd, BitOr, BitXor, Not, Sub};
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use ClusterMap;
+use Entry;
+use Iter;
+use VEBTree;
+
+/// A lazy iterator over the union of two `VEBTree`s, in ascending order.
+///
+/// Created by `VEBTree::union_iter`. It merges the two trees' iterators, so
+/// nothing is allocated; values present in both are yielded once.
+#[derive(Debug, Clone)]
+pub struct Union<'a> {
+    a: Peekable<Iter<'a>>,
+    b: Peekable<Iter<'a>>,
+}
+
+impl<'a> Iterator for Union<'a> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        let order = match (self.a.peek(), self.b.peek()) {
+            (Some(x), Some(y)) => x.cmp(y),
+            (Some(_), None) => Ordering::Less,
+            (None, _) => Ordering::Greater,
+        };
+        match order {
+            Ordering::Less => self.a.next(),
+            Ordering::Greater => self.b.next(),
+            Ordering::Equal => {
+                self.b.next();
+                self.a.next()
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a, b) = (self.a.len(), self.b.len());
+        (cmp::max(a, b), Some(a + b))
+    }
+}
+
+/// A lazy iterator over the intersection of two `VEBTree`s, in ascending order.
+///
+/// Created by `VEBTree::intersection_iter`. Rather than walking both trees, it
+/// leapfrogs: each tree's `ceil` skips straight past the values the other tree
+/// has just ruled out, starting from the smaller tree, so a sparse tree
+/// intersected with a dense one costs roughly one search per sparse element.
+#[derive(Debug, Clone)]
+pub struct Intersection<'a> {
+    small: &'a VEBTree,
+    large: &'a VEBTree,
+    // the lowest value not yet ruled out, or None once either tree runs out
+    from: Option<i64>,
+}
+
+impl<'a> Iterator for Intersection<'a> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        loop {
+            let x = self.small.ceil(self.from?);
+            let y = x.and_then(|x| self.large.ceil(x));
+            match (x, y) {
+                (Some(x), Some(y)) if x == y => {
+                    self.from = Some(x + 1);
+                    return Some(x);
+                }
+                (Some(_), Some(y)) => self.from = Some(y),
+                _ => {
+                    self.from = None;
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.from {
+            Some(_) => (0, Some(self.small.len())),
+            None => (0, Some(0)),
+        }
+    }
+}
+
+/// A lazy iterator over the values in one `VEBTree` but not another, in
+/// ascending order.
+///
+/// Created by `VEBTree::difference_iter`. When an element is found in the other
+/// tree, the iterator jumps to the end of the run of values the other tree holds
+/// there, so a dense other tree is skipped over rather than tested element by
+/// element.
+#[derive(Debug, Clone)]
+pub struct Difference<'a> {
+    a: &'a VEBTree,
+    b: &'a VEBTree,
+    // the lowest value not yet ruled out, or None once the first tree runs out
+    from: Option<i64>,
+}
+
+impl<'a> Iterator for Difference<'a> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        loop {
+            let x = match self.a.ceil(self.from?) {
+                Some(x) => x,
+                None => {
+                    self.from = None;
+                    return None;
+                }
+            };
+            if !self.b.can_store(x) {
+                self.from = Some(x + 1);
+                return Some(x);
+            }
+            match self.b.next_absent(x) {
+                Some(free) if free == x => {
+                    self.from = Some(x + 1);
+                    return Some(x);
+                }
+                Some(free) => self.from = Some(free),
+                // the other tree holds everything from x to the end of its universe
+                None => self.from = Some(self.b.universe()),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.from {
+            Some(_) => (0, Some(self.a.len())),
+            None => (0, Some(0)),
+        }
+    }
+}
+
+/// A lazy iterator over the values in exactly one of two `VEBTree`s, in
+/// ascending order.
+///
+/// Created by `VEBTree::symmetric_difference_iter`. Like `Union`, it merges the
+/// two trees' iterators, but values present in both are dropped.
+#[derive(Debug, Clone)]
+pub struct SymmetricDifference<'a> {
+    a: Peekable<Iter<'a>>,
+    b: Peekable<Iter<'a>>,
+}
+
+impl<'a> Iterator for SymmetricDifference<'a> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        loop {
+            let order = match (self.a.peek(), self.b.peek()) {
+                (Some(x), Some(y)) => x.cmp(y),
+                (Some(_), None) => Ordering::Less,
+                (None, _) => Ordering::Greater,
+            };
+            match order {
+                Ordering::Less => return self.a.next(),
+                Ordering::Greater => return self.b.next(),
+                Ordering::Equal => {
+                    self.a.next();
+                    self.b.next();
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a, b) = (self.a.len(), self.b.len());
+        (cmp::max(a, b) - cmp::min(a, b), Some(a + b))
+    }
+}
+
+// builds a node from its non-empty clusters and a few loose values that aren't
+// in them (such as the minimums of the nodes being combined), which may repeat
+fn assemble(universe: i64, children: ClusterMap<i64, VEBTree>, loose: &[i64]) -> VEBTree {
+    let mut tree = VEBTree::new(universe).unwrap();
+    let mut indices: Vec<i64> = children.keys().cloned().collect();
+    indices.sort_unstable();
+    if let (Some(&first), Some(&last)) = (indices.first(), indices.last()) {
+        let first_low = children[&first].min;
+        tree.len = children.values().map(VEBTree::len).sum();
+        tree.min = tree.index(first, first_low);
+        tree.max = tree.index(last, children[&last].max);
+        tree.summary = Some(Box::new(VEBTree::build_sorted(tree.summary_universe(), &indices)));
+        tree.children = children;
+        // the minimum is never stored in a cluster, so take it back out of its own
+        let cluster = tree.children.get_mut(&first).unwrap();
+        cluster.delete(first_low);
+        if cluster.is_empty() {
+            tree.remove_cluster(first);
+        }
+    }
+    for &x in loose {
+        tree.insert(x);
+    }
+    tree
+}
+
+// the union of two nodes of the same universe
+fn union_node(a: &VEBTree, b: &VEBTree) -> VEBTree {
+    if a.is_leaf() {
+        let mut tree = a.clone();
+        tree.bits |= b.bits;
+        tree.sync_bits();
+        return tree;
+    }
+    if b.is_empty() {
+        return a.clone();
+    } else if a.is_empty() {
+        return b.clone();
+    }
+    let mut children = a.children.clone();
+    for (&idx, cluster) in &b.children {
+        match children.entry(idx) {
+            Entry::Occupied(mut both) => {
+                let merged = union_node(both.get(), cluster);
+                both.insert(merged);
+            }
+            Entry::Vacant(only_b) => {
+                only_b.insert(cluster.clone());
+            }
+        }
+    }
+    assemble(a.universe, children, &[a.min, b.min])
+}
+
+// the intersection of two nodes of the same universe
+fn intersect_node(a: &VEBTree, b: &VEBTree) -> VEBTree {
+    if a.is_leaf() {
+        let mut tree = a.clone();
+        tree.bits &= b.bits;
+        tree.sync_bits();
+        return tree;
+    }
+    if a.is_empty() || b.is_empty() {
+        return VEBTree::new(a.universe).unwrap();
+    }
+    let (small, large) = if a.children.len() <= b.children.len() {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    let mut children = ClusterMap::new();
+    for (&idx, cluster) in &small.children {
+        if let Some(other) = large.children.get(&idx) {
+            let common = intersect_node(cluster, other);
+            if !common.is_empty() {
+                children.insert(idx, common);
+            }
+        }
+    }
+    let loose: Vec<i64> = [(a.min, b), (b.min, a)]
+        .iter()
+        .filter(|&&(min, other)| other.has(min))
+        .map(|&(min, _)| min)
+        .collect();
+    assemble(a.universe, children, &loose)
+}
+
+// the values of one node missing from another of the same universe
+fn difference_node(a: &VEBTree, b: &VEBTree) -> VEBTree {
+    if a.is_leaf() {
+        let mut tree = a.clone();
+        tree.bits &= !b.bits;
+        tree.sync_bits();
+        return tree;
+    }
+    if a.is_empty() || b.is_empty() {
+        return a.clone();
+    }
+    let mut children = ClusterMap::new();
+    for (&idx, cluster) in &a.children {
+        match b.children.get(&idx) {
+            Some(other) => {
+                let rest = difference_node(cluster, other);
+                if !rest.is_empty() {
+                    children.insert(idx, rest);
+                }
+            }
+            None => {
+                children.insert(idx, cluster.clone());
+            }
+        }
+    }
+    let loose: Vec<i64> = Some(a.min).filter(|&min| !b.has(min)).into_iter().collect();
+    let mut tree = assemble(a.universe, children, &loose);
+    // b's minimum isn't in its clusters, so it may have survived in a's
+    tree.delete(b.min);
+    tree
+}
+
+// the values in exactly one of two nodes of the same universe
+fn symmetric_difference_node(a: &VEBTree, b: &VEBTree) -> VEBTree {
+    if a.is_leaf() {
+        let mut tree = a.clone();
+        tree.bits ^= b.bits;
+        tree.sync_bits();
+        return tree;
+    }
+    if b.is_empty() {
+        return a.clone();
+    } else if a.is_empty() {
+        return b.clone();
+    }
+    let mut children = a.children.clone();
+    for (&idx, cluster) in &b.children {
+        match children.entry(idx) {
+            Entry::Occupied(both) => {
+                let rest = symmetric_difference_node(both.get(), cluster);
+                if rest.is_empty() {
+                    both.remove();
+                } else {
+                    *both.into_mut() = rest;
+                }
+            }
+            Entry::Vacant(only_b) => {
+                only_b.insert(cluster.clone());
+            }
+        }
+    }
+    let mut tree = assemble(a.universe, children, &[]);
+    // each minimum is in the result unless the other tree holds it too, which,
+    // as the minimums aren't in their own clusters, is exactly when it has been
+    // brought in with the other's clusters
+    if a.min != b.min {
+        for &min in &[a.min, b.min] {
+            if !tree.delete(min) {
+                tree.insert(min);
+            }
+        }
+    }
+    tree
+}
+
+// adds the values of one node to another of the same universe
+fn union_with_node(a: &mut VEBTree, b: &VEBTree) {
+    if a.is_leaf() {
+        a.bits |= b.bits;
+        a.sync_bits();
+        return;
+    }
+    if b.is_empty() {
+        return;
+    } else if a.is_empty() {
+        *a = b.clone();
+        return;
+    }
+    // b's clusters only hold values above its minimum, so once that is in
+    // place nothing merged in below can undercut a's minimum
+    if b.min < a.min {
+        a.insert(b.min);
+    }
+    let summary_universe = a.summary_universe();
+    for (&idx, cluster) in &b.children {
+        match a.children.entry(idx) {
+            Entry::Occupied(mut both) => {
+                let before = both.get().len;
+                union_with_node(both.get_mut(), cluster);
+                a.len += both.get().len - before;
+            }
+            Entry::Vacant(only_b) => {
+                a.len += cluster.len;
+                only_b.insert(cluster.clone());
+                a.summary
+                    .get_or_insert_with(|| Box::new(VEBTree::new(summary_universe).unwrap()))
+                    .insert(idx);
+            }
+        }
+    }
+    if b.len > 1 {
+        a.max = cmp::max(a.max, b.max);
+    }
+    a.insert(b.min);
+}
+
+// removes the values of one node that another of the same universe lacks
+fn intersect_with_node(a: &mut VEBTree, b: &VEBTree) {
+    if a.is_leaf() {
+        a.bits &= b.bits;
+        a.sync_bits();
+        return;
+    }
+    if a.is_empty() {
+        return;
+    } else if b.is_empty() {
+        *a = VEBTree::new(a.universe).unwrap();
+        return;
+    }
+    let keep_min = b.has(a.min);
+    // b's minimum isn't in its clusters, so intersecting them drops it from a's
+    let restore_b_min = b.min > a.min && a.has(b.min);
+    let mut dropped = vec![];
+    a.children.retain(|&idx, cluster| {
+        let kept = match b.children.get(&idx) {
+            Some(other) => {
+                intersect_with_node(cluster, other);
+                !cluster.is_empty()
+            }
+            None => false,
+        };
+        if !kept {
+            dropped.push(idx);
+        }
+        kept
+    });
+    if !dropped.is_empty() {
+        dropped.sort_unstable();
+        let summary = a.summary.as_mut().expect("summary not present");
+        summary.delete_sorted(&dropped);
+        if summary.is_empty() {
+            a.summary = None;
+        }
+    }
+    a.len = 1 + a.children.values().map(VEBTree::len).sum::<usize>();
+    a.max = a.cluster_max().unwrap_or(a.min);
+    if restore_b_min {
+        a.insert(b.min);
+    }
+    if !keep_min {
+        let min = a.min;
+        a.delete(min);
+    }
+}
+
+// removes the values of one node that another of the same universe holds
+fn difference_with_node(a: &mut VEBTree, b: &VEBTree) {
+    if a.is_leaf() {
+        a.bits &= !b.bits;
+        a.sync_bits();
+        return;
+    }
+    if a.is_empty() || b.is_empty() {
+        return;
+    }
+    let drop_min = b.has(a.min);
+    for (&idx, other) in &b.children {
+        let emptied = match a.children.get_mut(&idx) {
+            Some(cluster) => {
+                let before = cluster.len;
+                difference_with_node(cluster, other);
+                a.len -= before - cluster.len;
+                cluster.is_empty()
+            }
+            None => false,
+        };
+        if emptied {
+            a.remove_cluster(idx);
+        }
+    }
+    a.max = a.cluster_max().unwrap_or(a.min);
+    if drop_min {
+        let min = a.min;
+        a.delete(min);
+    }
+    // b's minimum isn't in its clusters, so it may have survived in a's
+    a.delete(b.min);
+}
+
+// whether every value of one node is in another of the same universe. Once a's
+// minimum is known to be in b, b's minimum is at most a's, so it can't be in
+// a's clusters and they only need comparing with b's.
+fn subset_node(a: &VEBTree, b: &VEBTree) -> bool {
+    if a.is_leaf() {
+        return a.bits & !b.bits == 0;
+    }
+    if a.is_empty() {
+        return true;
+    }
+    if a.len > b.len || !b.has(a.min) {
+        return false;
+    }
+    a.children.iter().all(|(idx, cluster)| {
+        b.children.get(idx).is_some_and(|other| subset_node(cluster, other))
+    })
+}
+
+// whether two nodes of the same universe have no values in common. A shared
+// value is either one of the minimums or in the same cluster of both.
+fn disjoint_node(a: &VEBTree, b: &VEBTree) -> bool {
+    if a.is_leaf() {
+        return a.bits & b.bits == 0;
+    }
+    if a.is_empty() || b.is_empty() || a.max < b.min || b.max < a.min {
+        return true;
+    }
+    if b.has(a.min) || a.has(b.min) {
+        return false;
+    }
+    let (small, large) = if a.children.len() <= b.children.len() {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    small.children.iter().all(|(idx, cluster)| {
+        large.children.get(idx).is_none_or(|other| disjoint_node(cluster, other))
+    })
+}
+
+// the values below limit that a node doesn't hold; limit is the node's universe,
+// except in the last cluster of a universe that isn't a multiple of the cluster
+// size, where it cuts the cluster short
+fn complement_node(a: &VEBTree, limit: i64) -> VEBTree {
+    if a.is_leaf() {
+        let mut tree = VEBTree::new(a.universe).unwrap();
+        tree.bits = !a.bits & (!0 >> (64 - limit));
+        tree.sync_bits();
+        return tree;
+    }
+    if a.is_empty() {
+        return VEBTree::from_range(0, limit - 1, a.universe).unwrap();
+    }
+    let sqrt = a.sqrt_universe();
+    let mut children = ClusterMap::new();
+    for idx in 0..a.high(limit - 1) + 1 {
+        let cluster_limit = cmp::min(sqrt, limit - a.index(idx, 0));
+        match a.children.get(&idx) {
+            Some(cluster) if cluster.len as i64 == cluster_limit => {}
+            Some(cluster) => {
+                children.insert(idx, complement_node(cluster, cluster_limit));
+            }
+            None => {
+                children.insert(idx, VEBTree::from_range(0, cluster_limit - 1, sqrt).unwrap());
+            }
+        }
+    }
+    let mut tree = assemble(a.universe, children, &[]);
+    // a's minimum isn't in its clusters, so it has been brought in as absent
+    tree.delete(a.min);
+    tree
+}
+
+impl VEBTree {
+    /// Returns a lazy iterator over the values in either this tree or `other`,
+    /// in ascending order. The trees may have different universes.
+    pub fn union_iter<'a>(&'a self, other: &'a VEBTree) -> Union<'a> {
+        Union {
+            a: self.iter().peekable(),
+            b: other.iter().peekable(),
+        }
+    }
+
+    /// Returns a lazy iterator over the values in both this tree and `other`, in
+    /// ascending order. The trees may have different universes.
+    pub fn intersection_iter<'a>(&'a self, other: &'a VEBTree) -> Intersection<'a> {
+        let (small, large) = if self.len() <= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        Intersection {
+            small,
+            large,
+            from: Some(0),
+        }
+    }
+
+    /// Returns a lazy iterator over the values in this tree but not in `other`,
+    /// in ascending order. The trees may have different universes.
+    pub fn difference_iter<'a>(&'a self, other: &'a VEBTree) -> Difference<'a> {
+        Difference {
+            a: self,
+            b: other,
+            from: Some(0),
+        }
+    }
+
+    /// Returns a lazy iterator over the values in exactly one of this tree and
+    /// `other`, in ascending order. The trees may have different universes.
+    pub fn symmetric_difference_iter<'a>(&'a self, other: &'a VEBTree) -> SymmetricDifference<'a> {
+        SymmetricDifference {
+            a: self.iter().peekable(),
+            b: other.iter().peekable(),
+        }
+    }
+
+    /// Returns a new tree holding every value in either this tree or `other`.
+    ///
+    /// When the universes match, the trees are combined cluster by cluster:
+    /// clusters only one side has are cloned whole, and only clusters both sides
+    /// have are merged further down. Otherwise the result has the larger of the
+    /// two universes, and is built by inserting the smaller tree's elements into
+    /// a copy of the larger.
+    pub fn union(&self, other: &VEBTree) -> VEBTree {
+        if self.universe == other.universe {
+            return union_node(self, other);
+        }
+        let (small, large) = if self.universe < other.universe {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let mut tree = large.clone();
+        tree.insert_sorted(&small.iter().collect::<Vec<_>>());
+        tree
+    }
+
+    /// Returns a new tree holding the values in both this tree and `other`, with
+    /// the smaller of the two universes.
+    ///
+    /// When the universes match, only clusters present in both trees are
+    /// visited, so intersecting a large tree with a sparse one costs roughly in
+    /// proportion to the sparse one. Otherwise the result is built from
+    /// `intersection_iter`.
+    pub fn intersection(&self, other: &VEBTree) -> VEBTree {
+        if self.universe == other.universe {
+            return intersect_node(self, other);
+        }
+        let universe = cmp::min(self.universe, other.universe);
+        VEBTree::build_sorted(universe, &self.intersection_iter(other).collect::<Vec<_>>())
+    }
+
+    /// Returns a new tree with this tree's universe, holding the values in this
+    /// tree but not in `other`.
+    ///
+    /// When the universes match, clusters `other` doesn't have are cloned whole
+    /// and only shared clusters are compared further down. Otherwise the result
+    /// is built from `difference_iter`.
+    pub fn difference(&self, other: &VEBTree) -> VEBTree {
+        if self.universe == other.universe {
+            return difference_node(self, other);
+        }
+        VEBTree::build_sorted(self.universe, &self.difference_iter(other).collect::<Vec<_>>())
+    }
+
+    /// Returns a new tree holding the values in exactly one of this tree and
+    /// `other`, with the larger of the two universes.
+    ///
+    /// When the universes match, the trees are combined cluster by cluster as in
+    /// `union`, with shared clusters that cancel out dropped. Otherwise the
+    /// result is built from `symmetric_difference_iter`.
+    pub fn symmetric_difference(&self, other: &VEBTree) -> VEBTree {
+        if self.universe == other.universe {
+            return symmetric_difference_node(self, other);
+        }
+        let universe = cmp::max(self.universe, other.universe);
+        let elements: Vec<i64> = self.symmetric_difference_iter(other).collect();
+        VEBTree::build_sorted(universe, &elements)
+    }
+
+    /// Adds every value in `other` to this tree. When the universes match, only
+    /// the clusters `other` has are visited: those this tree lacks are cloned,
+    /// and shared ones are merged in place. Otherwise the values are inserted
+    /// one cluster run at a time. Panics if `other` holds a value outside this
+    /// tree's universe.
+    pub fn union_with(&mut self, other: &VEBTree) {
+        if self.universe == other.universe {
+            union_with_node(self, other);
+        } else {
+            assert!(
+                other.maximum().is_none_or(|max| self.can_store(max)),
+                "value outside the universe"
+            );
+            self.insert_sorted(&other.iter().collect::<Vec<_>>());
+        }
+    }
+
+    /// Removes every value not in `other` from this tree. When the universes
+    /// match, clusters `other` lacks are dropped outright and shared ones are
+    /// intersected in place.
+    pub fn intersect_with(&mut self, other: &VEBTree) {
+        if self.universe == other.universe {
+            intersect_with_node(self, other);
+        } else {
+            self.retain(|x| other.has(x));
+        }
+    }
+
+    /// Removes every value in `other` from this tree. When the universes match,
+    /// only the clusters `other` has are visited, and any left empty are freed.
+    pub fn difference_with(&mut self, other: &VEBTree) {
+        if self.universe == other.universe {
+            difference_with_node(self, other);
+        } else {
+            self.bulk_delete(other.iter());
+        }
+    }
+
+    /// Returns a new tree with the same universe holding exactly the values in
+    /// `[0, universe)` this tree doesn't. Full clusters are skipped, missing ones
+    /// are built full in one go, and only partly filled clusters are recursed
+    /// into, so a nearly full tree has a cheap complement.
+    pub fn complement(&self) -> VEBTree {
+        complement_node(self, self.universe)
+    }
+
+    /// Returns true if every element of this tree is also in `other`. When the
+    /// universes match this compares the trees cluster by cluster, bailing out
+    /// at the first cluster `other` doesn't have or has fewer elements in.
+    pub fn is_subset(&self, other: &VEBTree) -> bool {
+        if self.len() > other.len() {
+            return false;
+        }
+        if self.universe == other.universe {
+            return subset_node(self, other);
+        }
+        self.iter().all(|x| other.has(x))
+    }
+
+    /// Returns true if every element of `other` is also in this tree.
+    pub fn is_superset(&self, other: &VEBTree) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns true if the trees have no elements in common. Trees whose
+    /// elements span separate ranges are told apart without a search; otherwise,
+    /// when the universes match, only clusters both trees have are compared.
+    pub fn is_disjoint(&self, other: &VEBTree) -> bool {
+        if self.is_empty()
+            || other.is_empty()
+            || self.max < other.min
+            || other.max < self.min
+        {
+            return true;
+        }
+        if self.universe == other.universe {
+            return disjoint_node(self, other);
+        }
+        self.intersection_iter(other).next().is_none()
+    }
+}
+
+/// `!&tree` is the tree's `complement`.
+impl Not for &VEBTree {
+    type Output = VEBTree;
+
+    fn not(self) -> VEBTree {
+        self.complement()
+    }
+}
+
+/// `&a | &b` is `a.union(&b)`.
+impl BitOr<&VEBTree> for &VEBTree {
+    type Output = VEBTree;
+
+    fn bitor(self, other: &VEBTree) -> VEBTree {
+        self.union(other)
+    }
+}
+
+/// `&a & &b` is `a.intersection(&b)`.
+impl BitAnd<&VEBTree> for &VEBTree {
+    type Output = VEBTree;
+
+    fn bitand(self, other: &VEBTree) -> VEBTree {
+        self.intersection(other)
+    }
+}
+
+/// `&a ^ &b` is `a.symmetric_difference(&b)`.
+impl BitXor<&VEBTree> for &VEBTree {
+    type Output = VEBTree;
+
+    fn bitxor(self, other: &VEBTree) -> VEBTree {
+        self.symmetric_difference(other)
+    }
+}
+
+/// `&a - &b` is `a.difference(&b)`.
+impl Sub<&VEBTree> for &VEBTree {
+    type Output = VEBTree;
+
+    fn sub(self, other: &VEBTree) -> VEBTree {
+        self.difference(other)
+    }
+}
+
+#[cfg(test)]
+fn oracle_pairs() -> Vec<(VEBTree, VEBTree)> {
+    let build = |universe: i64, step: i64, count: i64| {
+        let mut tree = VEBTree::new(universe).unwrap();
+        for i in 0..count {
+            tree.insert(i * step % universe);
+        }
+        tree
+    };
+    vec![
+        (build(1000, 3, 200), build(1000, 5, 150)),
+        (build(1 << 20, 7919, 300), build(64, 3, 20)),
+        (build(256, 1, 0), build(256, 11, 30)),
+        (build(256, 11, 30), build(4096, 1, 0)),
+        (build(256, 1, 0), build(16, 1, 0)),
+        (build(100, 1, 100), build(100, 2, 50)),
+    ]
+}
+
+// random pairs of trees over the same universe, at a spread of densities, so
+// that the structural operations meet shared, lone and leaf clusters
+#[cfg(test)]
+fn random_pairs() -> Vec<(VEBTree, VEBTree)> {
+    let mut pairs = vec![];
+    for &(universe, count_a, count_b) in &[
+        (2, 1, 2),
+        (64, 20, 40),
+        (100, 60, 30),
+        (5000, 3000, 200),
+        (1 << 16, 500, 20000),
+        (1 << 24, 2000, 2000),
+    ] {
+        let (a, _) = ::random_tree(universe, count_a, 5);
+        let (b, _) = ::random_tree(universe, count_b, 6);
+        pairs.push((a.clone(), a.clone()));
+        pairs.push((a, b));
+    }
+    pairs
+}
+
+#[test]
+fn union_iter_matches_btreeset() {
+    use std::collections::BTreeSet;
+
+    for (a, b) in oracle_pairs() {
+        let (x, y): (BTreeSet<i64>, BTreeSet<i64>) = (a.iter().collect(), b.iter().collect());
+        let union = a.union_iter(&b);
+        assert_eq!(union.size_hint().1, Some(a.len() + b.len()));
+        assert!(union.eq(x.union(&y).cloned()));
+        assert!(b.union_iter(&a).eq(y.union(&x).cloned()));
+    }
+}
+
+#[test]
+fn intersection_iter_matches_btreeset() {
+    use std::collections::BTreeSet;
+
+    let mut pairs = oracle_pairs();
+    let mut dense = VEBTree::new(1 << 20).unwrap();
+    dense.insert_sorted(&(0..200000).collect::<Vec<_>>());
+    let mut sparse = VEBTree::new(1 << 16).unwrap();
+    for i in 0..50 {
+        sparse.insert(i * 1301);
+    }
+    pairs.push((dense, sparse));
+    for (a, b) in pairs {
+        let (x, y): (BTreeSet<i64>, BTreeSet<i64>) = (a.iter().collect(), b.iter().collect());
+        assert!(a.intersection_iter(&b).eq(x.intersection(&y).cloned()));
+        assert!(b.intersection_iter(&a).eq(y.intersection(&x).cloned()));
+    }
+}
+
+#[test]
+fn subset_superset_disjoint() {
+    use std::collections::BTreeSet;
+
+    let mut pairs = oracle_pairs();
+    let mut evens = VEBTree::new(1000).unwrap();
+    let mut multiples_of_four = VEBTree::new(64).unwrap();
+    for x in 0..16 {
+        evens.insert(x * 2);
+        multiples_of_four.insert(x * 4);
+    }
+    pairs.push((multiples_of_four, evens));
+    for (a, b) in pairs {
+        let (x, y): (BTreeSet<i64>, BTreeSet<i64>) = (a.iter().collect(), b.iter().collect());
+        assert_eq!(a.is_subset(&b), x.is_subset(&y));
+        assert_eq!(b.is_subset(&a), y.is_subset(&x));
+        assert_eq!(a.is_superset(&b), x.is_superset(&y));
+        assert_eq!(a.is_disjoint(&b), x.is_disjoint(&y));
+    }
+
+    let mut odds = VEBTree::new(100).unwrap();
+    let mut evens = VEBTree::new(100).unwrap();
+    for x in 0..50 {
+        odds.insert(x * 2 + 1);
+        evens.insert(x * 2);
+    }
+    assert!(odds.is_disjoint(&evens));
+    assert!(!odds.is_subset(&evens));
+    evens.insert(31);
+    assert!(!odds.is_disjoint(&evens));
+}
+
+#[test]
+fn subset_of_related_trees() {
+    use std::collections::BTreeSet;
+
+    for (a, b) in random_pairs() {
+        let (x, y): (BTreeSet<i64>, BTreeSet<i64>) = (a.iter().collect(), b.iter().collect());
+        assert_eq!(a.is_subset(&b), x.is_subset(&y));
+        assert_eq!(b.is_subset(&a), y.is_subset(&x));
+        let (union, intersection) = (a.union(&b), a.intersection(&b));
+        assert!(a.is_subset(&union) && b.is_subset(&union) && union.is_superset(&a));
+        assert!(intersection.is_subset(&a) && intersection.is_subset(&b));
+        assert!(a.is_subset(&a));
+        assert!(VEBTree::new(a.universe()).unwrap().is_subset(&a));
+        // one element missing anywhere is enough
+        if let Some(max) = union.maximum() {
+            let mut short = union.clone();
+            short.delete(max);
+            assert!(!union.is_subset(&short));
+            let mut moved = short.clone();
+            moved.insert(union.find_free_ge(0).unwrap_or(max));
+            assert_eq!(union.is_subset(&moved), moved == union);
+        }
+    }
+}
+
+#[test]
+fn disjoint_edge_cases() {
+    use std::collections::BTreeSet;
+
+    for (a, b) in random_pairs() {
+        let (x, y): (BTreeSet<i64>, BTreeSet<i64>) = (a.iter().collect(), b.iter().collect());
+        assert_eq!(a.is_disjoint(&b), x.is_disjoint(&y));
+        let (rest, other) = (a.difference(&b), b.difference(&a));
+        assert!(rest.is_disjoint(&other) && rest.is_disjoint(&b));
+        assert!(VEBTree::new(a.universe()).unwrap().is_disjoint(&a));
+        assert!(a.is_disjoint(&VEBTree::new(a.universe()).unwrap()));
+        // sharing a single element, wherever it is, is enough
+        for x in a.iter().step_by(97) {
+            let mut other = other.clone();
+            other.insert(x);
+            assert!(!rest.union(&a).is_disjoint(&other));
+        }
+    }
+    let low = VEBTree::from_range(0, 499, 1 << 16).unwrap();
+    let high = VEBTree::from_range(500, 999, 1 << 16).unwrap();
+    assert!(low.is_disjoint(&high) && high.is_disjoint(&low));
+    let touching = VEBTree::from_range(499, 999, 1 << 16).unwrap();
+    assert!(!low.is_disjoint(&touching));
+}
+
+#[test]
+fn difference_iter_matches_btreeset() {
+    use std::collections::BTreeSet;
+
+    let mut pairs = oracle_pairs();
+    let mut dense = VEBTree::new(1 << 20).unwrap();
+    dense.insert_sorted(&(0..200000).filter(|x| x % 1000 != 999).collect::<Vec<_>>());
+    let mut sparse = VEBTree::new(1 << 20).unwrap();
+    sparse.insert_sorted(&(0..1000).map(|x| x * 997).collect::<Vec<_>>());
+    pairs.push((sparse.clone(), dense.clone()));
+    pairs.push((dense.clone(), sparse));
+    pairs.push((dense.clone(), dense));
+    for (a, b) in pairs {
+        let (x, y): (BTreeSet<i64>, BTreeSet<i64>) = (a.iter().collect(), b.iter().collect());
+        assert!(a.difference_iter(&b).eq(x.difference(&y).cloned()));
+        assert!(b.difference_iter(&a).eq(y.difference(&x).cloned()));
+    }
+}
+
+#[test]
+fn symmetric_difference_iter_matches_btreeset() {
+    use std::collections::BTreeSet;
+
+    let mut pairs = oracle_pairs();
+    let evens = VEBTree::from_sorted_slice(&(0..500).map(|x| x * 2).collect::<Vec<_>>(), 1000);
+    let odds = VEBTree::from_sorted_slice(&(0..500).map(|x| x * 2 + 1).collect::<Vec<_>>(), 1000);
+    let (evens, odds) = (evens.unwrap(), odds.unwrap());
+    pairs.push((evens.clone(), odds));
+    pairs.push((evens.clone(), evens));
+    for (a, b) in pairs {
+        let (x, y): (BTreeSet<i64>, BTreeSet<i64>) = (a.iter().collect(), b.iter().collect());
+        let expected: Vec<i64> = x.symmetric_difference(&y).cloned().collect();
+        let (lower, upper) = a.symmetric_difference_iter(&b).size_hint();
+        assert!(lower <= expected.len() && expected.len() <= upper.unwrap());
+        assert!(a.symmetric_difference_iter(&b).eq(expected.iter().cloned()));
+        assert!(b.symmetric_difference_iter(&a).eq(expected.iter().cloned()));
+    }
+}
+
+#[test]
+fn next_absent() {
+    let mut tree = VEBTree::new(1 << 16).unwrap();
+    tree.insert_sorted(&(100..5000).collect::<Vec<_>>());
+    tree.insert(5001);
+    assert_eq!(tree.next_absent(0), Some(0));
+    assert_eq!(tree.next_absent(100), Some(5000));
+    assert_eq!(tree.next_absent(5001), Some(5002));
+    tree.insert_sorted(&(5000..65536).collect::<Vec<_>>());
+    assert_eq!(tree.next_absent(100), None);
+    assert_eq!(tree.next_absent(99), Some(99));
+    let mut leaf = VEBTree::new(40).unwrap();
+    leaf.insert_sorted(&(0..40).collect::<Vec<_>>());
+    assert_eq!(leaf.next_absent(3), None);
+}
+
+#[test]
+fn union_matches_btreeset() {
+    use std::collections::BTreeSet;
+
+    for (a, b) in random_pairs().into_iter().chain(oracle_pairs()) {
+        let (x, y): (BTreeSet<i64>, BTreeSet<i64>) = (a.iter().collect(), b.iter().collect());
+        let union = a.union(&b);
+        union.check_invariants().unwrap();
+        assert_eq!(union.universe(), cmp::max(a.universe(), b.universe()));
+        assert!(union.iter().eq(x.union(&y).cloned()));
+        assert_eq!(b.union(&a), union);
+    }
+}
+
+#[test]
+fn complement() {
+    let pairs = random_pairs().into_iter().chain(oracle_pairs());
+    // the complement of a sparse tree is dense, so keep the universes small
+    for (tree, _) in pairs.filter(|(tree, _)| tree.universe() <= 1 << 16) {
+        let complement = tree.complement();
+        complement.check_invariants().unwrap();
+        assert_eq!(complement.universe(), tree.universe());
+        assert_eq!(tree.union(&complement).len() as i64, tree.universe());
+        assert!(tree.is_disjoint(&complement));
+        assert_eq!(complement.complement(), tree);
+    }
+    let empty = VEBTree::new(1000).unwrap();
+    assert!(empty.complement().iter().eq(0..1000));
+    assert!(empty.complement().complement().is_empty());
+
+    // partly filled, full and missing clusters, with a short last cluster
+    let mut tree = VEBTree::from_range(0, 299, 1000).unwrap();
+    tree.insert_range(600, 999);
+    tree.delete(100);
+    tree.delete(999);
+    let complement = !&tree;
+    complement.check_invariants().unwrap();
+    assert!(complement.iter().eq(Some(100).into_iter().chain(300..600).chain(Some(999))));
+    assert_eq!(!&complement, tree);
+    assert!((!&VEBTree::from_range(0, 4999, 5000).unwrap()).is_empty());
+}
+
+#[test]
+fn intersection_matches_btreeset() {
+    use std::collections::BTreeSet;
+
+    for (a, b) in random_pairs().into_iter().chain(oracle_pairs()) {
+        let (x, y): (BTreeSet<i64>, BTreeSet<i64>) = (a.iter().collect(), b.iter().collect());
+        let intersection = a.intersection(&b);
+        intersection.check_invariants().unwrap();
+        assert_eq!(intersection.universe(), cmp::min(a.universe(), b.universe()));
+        assert!(intersection.iter().eq(x.intersection(&y).cloned()));
+        assert_eq!(b.intersection(&a), intersection);
+    }
+    let evens = VEBTree::from_sorted_slice(&(0..500).map(|x| x * 2).collect::<Vec<_>>(), 1000);
+    let odds = VEBTree::from_sorted_slice(&(0..500).map(|x| x * 2 + 1).collect::<Vec<_>>(), 1000);
+    let empty = evens.unwrap().intersection(&odds.unwrap());
+    empty.check_invariants().unwrap();
+    assert!(empty.is_empty());
+    assert_eq!((empty.minimum(), empty.maximum()), (None, None));
+}
+
+#[test]
+fn difference_matches_btreeset() {
+    use std::collections::BTreeSet;
+
+    for (a, b) in random_pairs().into_iter().chain(oracle_pairs()) {
+        let (x, y): (BTreeSet<i64>, BTreeSet<i64>) = (a.iter().collect(), b.iter().collect());
+        for &(a, b, x, y) in &[(&a, &b, &x, &y), (&b, &a, &y, &x)] {
+            let difference = a.difference(b);
+            difference.check_invariants().unwrap();
+            assert_eq!(difference.universe(), a.universe());
+            assert!(difference.iter().eq(x.difference(y).cloned()));
+        }
+        // taking away a superset leaves a valid, empty tree
+        let empty = a.difference(&a.union(&b));
+        empty.check_invariants().unwrap();
+        assert!(empty.is_empty());
+        assert_eq!((empty.minimum(), empty.maximum()), (None, None));
+    }
+}
+
+#[test]
+fn symmetric_difference_matches_btreeset() {
+    use std::collections::BTreeSet;
+
+    for (a, b) in random_pairs().into_iter().chain(oracle_pairs()) {
+        let (x, y): (BTreeSet<i64>, BTreeSet<i64>) = (a.iter().collect(), b.iter().collect());
+        let xor = a.symmetric_difference(&b);
+        xor.check_invariants().unwrap();
+        assert_eq!(xor.universe(), cmp::max(a.universe(), b.universe()));
+        assert!(xor.iter().eq(x.symmetric_difference(&y).cloned()));
+        assert_eq!(b.symmetric_difference(&a), xor);
+        // applying the delta again gets back the other side
+        if a.universe() == b.universe() {
+            assert_eq!(a.symmetric_difference(&xor), b);
+        }
+
+        let same = a.symmetric_difference(&a);
+        same.check_invariants().unwrap();
+        assert!(same.is_empty());
+    }
+
+    let evens = VEBTree::from_sorted_slice(&(0..500).map(|x| x * 2).collect::<Vec<_>>(), 1000);
+    let odds = VEBTree::from_sorted_slice(&(0..500).map(|x| x * 2 + 1).collect::<Vec<_>>(), 1000);
+    let (evens, odds) = (evens.unwrap(), odds.unwrap());
+    assert_eq!(evens.symmetric_difference(&odds), evens.union(&odds));
+}
+
+#[test]
+fn in_place_ops_match_owned() {
+    for (a, b) in random_pairs().into_iter().chain(oracle_pairs()) {
+        for &(a, b) in &[(&a, &b), (&b, &a)] {
+            if b.maximum().is_none_or(|max| a.can_store(max)) {
+                let mut union = a.clone();
+                union.union_with(b);
+                union.check_invariants().unwrap();
+                assert!(union.iter().eq(a.union(b).iter()));
+            }
+
+            let mut intersection = a.clone();
+            intersection.intersect_with(b);
+            intersection.check_invariants().unwrap();
+            assert!(intersection.iter().eq(a.intersection(b).iter()));
+
+            let mut difference = a.clone();
+            difference.difference_with(b);
+            difference.check_invariants().unwrap();
+            assert_eq!(difference, a.difference(b));
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "value outside the universe")]
+fn union_with_smaller_universe() {
+    let mut tree = VEBTree::new(16).unwrap();
+    tree.union_with(&VEBTree::from_sorted_slice(&[3, 40], 64).unwrap());
+}
+
+#[test]
+fn operators() {
+    use std::collections::BTreeSet;
+
+    let pairs = random_pairs();
+    for window in pairs.windows(2) {
+        let ((live, referenced), (tombstones, _)) = (&window[0], &window[1]);
+        let set = |tree: &VEBTree| tree.iter().collect::<BTreeSet<i64>>();
+        let (x, y, z) = (set(live), set(referenced), set(tombstones));
+
+        let tree = &(live & referenced) - tombstones;
+        assert!(tree.iter().eq((&(&x & &y) - &z).into_iter()));
+        let tree = &(live | referenced) ^ tombstones;
+        assert!(tree.iter().eq((&(&x | &y) ^ &z).into_iter()));
+        assert_eq!(&(live - referenced) | &(live & referenced), *live);
+        assert_eq!(live ^ live, VEBTree::new(live.universe()).unwrap());
+    }
+}
@@ -0,0 +1,172 @@
+//! Structural statistics about a `VEBTree`.
+
+use VEBTree;
+
+/// Counts describing how a `VEBTree` is laid out in memory, returned by
+/// `VEBTree::stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VEBStats {
+    /// The number of nodes allocated, counting clusters and summaries at every
+    /// level as well as the root; the same as `node_count`.
+    pub nodes: usize,
+    /// The depth of the deepest allocated node, with the root at depth 1. At
+    /// most `depth()`, and less when the lower levels are never reached.
+    pub max_depth: usize,
+    /// The number of non-empty clusters directly below the root.
+    pub top_clusters: usize,
+    /// The number of elements; the same as `len`.
+    pub len: usize,
+}
+
+/// Aggregate statistics about the elements of a `VEBTree` and how they are
+/// spread over its universe, returned by `VEBTree::statistics`. Only available
+/// with the `std` feature, which the square root for `std_dev` needs.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VEBTreeStats {
+    /// The universe of the tree.
+    pub universe: i64,
+    /// The number of elements.
+    pub len: usize,
+    /// The lowest element, if any.
+    pub min: Option<i64>,
+    /// The highest element, if any.
+    pub max: Option<i64>,
+    /// The depth of the tree's recursion; see `VEBTree::depth`.
+    pub depth: usize,
+    /// The number of nodes allocated; see `VEBTree::node_count`.
+    pub node_count: usize,
+    /// The fraction of the universe that is stored, `len / universe`.
+    pub density: f64,
+    /// The arithmetic mean of the elements, or 0 for an empty tree.
+    pub mean: f64,
+    /// The population standard deviation of the elements, or 0 for an empty
+    /// tree.
+    pub std_dev: f64,
+    /// The number of maximal runs of consecutive elements, as counted by
+    /// `run_length_encode`.
+    pub segment_count: usize,
+}
+
+impl VEBTree {
+    /// Gathers `VEBTreeStats` for this tree. The elements are visited once,
+    /// with the mean and standard deviation kept up to date as they go
+    /// (Welford's method), so large elements can't overflow a running sum.
+    #[cfg(feature = "std")]
+    pub fn statistics(&self) -> VEBTreeStats {
+        let (mut count, mut mean, mut squares) = (0.0, 0.0, 0.0);
+        let mut segment_count = 0;
+        let mut last = None;
+        self.for_each(|x| {
+            count += 1.0;
+            let delta = x as f64 - mean;
+            mean += delta / count;
+            squares += delta * (x as f64 - mean);
+            if last != Some(x - 1) {
+                segment_count += 1;
+            }
+            last = Some(x);
+        });
+        VEBTreeStats {
+            universe: self.universe,
+            len: self.len(),
+            min: self.minimum(),
+            max: self.maximum(),
+            depth: self.depth(),
+            node_count: self.node_count(),
+            density: self.len() as f64 / self.universe as f64,
+            mean,
+            std_dev: if count > 0.0 { (squares / count).sqrt() } else { 0.0 },
+            segment_count,
+        }
+    }
+
+    /// Gathers `VEBStats` for this tree in a single walk over its nodes.
+    pub fn stats(&self) -> VEBStats {
+        let mut stats = VEBStats {
+            nodes: 0,
+            max_depth: 0,
+            top_clusters: self.children.len(),
+            len: self.len(),
+        };
+        self.count_nodes(1, &mut stats);
+        stats
+    }
+
+    fn count_nodes(&self, depth: usize, stats: &mut VEBStats) {
+        stats.nodes += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+        for cluster in self.children.values() {
+            cluster.count_nodes(depth + 1, stats);
+        }
+        if let Some(ref summary) = self.summary {
+            summary.count_nodes(depth + 1, stats);
+        }
+    }
+}
+
+#[test]
+fn stats() {
+    let mut tree = VEBTree::new(256).unwrap();
+    let empty = VEBStats {
+        nodes: 1,
+        max_depth: 1,
+        top_clusters: 0,
+        len: 0,
+    };
+    assert_eq!(tree.stats(), empty);
+    // the minimum stays in the root; 208 goes into cluster 13, a leaf, and 13
+    // into the summary, another leaf
+    tree.insert(1);
+    tree.insert(13 * 16);
+    let expected = VEBStats {
+        nodes: 3,
+        max_depth: 2,
+        top_clusters: 1,
+        len: 2,
+    };
+    assert_eq!(tree.stats(), expected);
+    tree.insert(13 * 16 + 5);
+    tree.insert(2 * 16);
+    assert_eq!(tree.stats().nodes, 4);
+    assert_eq!(tree.stats().top_clusters, 2);
+
+    let mut large = VEBTree::new(1 << 20).unwrap();
+    for x in 0..5000 {
+        large.insert(x * 211 % (1 << 20));
+    }
+    let stats = large.stats();
+    assert_eq!(stats.nodes, large.node_count());
+    assert_eq!(stats.len, large.len());
+    assert!(stats.max_depth <= large.depth());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn statistics() {
+    let tree = VEBTree::from_sorted_slice(&[2, 3, 4, 10, 21], 32).unwrap();
+    let stats = tree.statistics();
+    assert_eq!(stats.universe, 32);
+    assert_eq!(stats.len, 5);
+    assert_eq!((stats.min, stats.max), (Some(2), Some(21)));
+    assert_eq!(stats.depth, 1);
+    assert_eq!(stats.node_count, 1);
+    assert_eq!(stats.segment_count, 3);
+    assert!((stats.density - 5.0 / 32.0).abs() < 1e-12);
+    assert!((stats.mean - 8.0).abs() < 1e-12);
+    // squared deviations: 36 + 25 + 16 + 4 + 169 = 250
+    assert!((stats.std_dev - 50f64.sqrt()).abs() < 1e-12);
+
+    let empty = VEBTree::new(100).unwrap().statistics();
+    assert_eq!((empty.len, empty.min, empty.max, empty.segment_count), (0, None, None, 0));
+    assert_eq!((empty.density, empty.mean, empty.std_dev), (0.0, 0.0, 0.0));
+
+    let mut large = VEBTree::from_range(1000, 1999, 1 << 40).unwrap();
+    large.insert((1 << 40) - 1);
+    let stats = large.statistics();
+    assert_eq!((stats.len, stats.min, stats.max), (large.len(), large.minimum(), large.maximum()));
+    assert_eq!((stats.depth, stats.node_count), (large.depth(), large.node_count()));
+    assert_eq!(stats.segment_count, large.run_length_encode().len());
+    let mean = large.iter().map(|x| x as f64).sum::<f64>() / large.len() as f64;
+    assert!((stats.mean - mean).abs() / mean < 1e-9);
+}
@@ -0,0 +1,30 @@
+//! Builds against the crate using only `core` and `alloc`. Run with
+//! `cargo test --no-default-features` to check the crate itself without `std`;
+//! `cargo build --no-default-features --target thumbv7em-none-eabihf` checks it
+//! on a target with no `std` at all.
+
+#![no_std]
+
+extern crate alloc;
+extern crate veb_rs;
+
+use alloc::vec::Vec;
+
+use veb_rs::VEBTree;
+
+#[test]
+fn alloc_only() {
+    let mut tree = VEBTree::new(1 << 20).unwrap();
+    for x in (0..1000).map(|x| x * 1013 % (1 << 20)) {
+        tree.insert(x);
+    }
+    assert_eq!(tree.len(), 1000);
+    let elements: Vec<i64> = tree.iter().collect();
+    assert!(elements.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(tree.find_next(elements[10]), Some(elements[11]));
+    for &x in &elements[..500] {
+        assert!(tree.delete(x));
+    }
+    assert!(tree.iter().eq(elements[500..].iter().cloned()));
+    tree.check_invariants().unwrap();
+}